@@ -1,75 +1,109 @@
-mod format_test;
-
 use std::io;
 use std::sync::Arc;
 
-use linefeed::{Interface, ReadResult};
+use linefeed::complete::{Completer, Completion, Suffix};
+use linefeed::{Interface, Prompter, ReadResult, Terminal};
+
+use marin::{Arg, Command, CommandRegistry, Error, HelpFormat, Marin, ValueType};
+
+enum Action {
+    Help(Option<String>),
+    Exit,
+    Done,
+}
+
+fn commands() -> CommandRegistry<(), Action> {
+    CommandRegistry::new()
+        .command(Command::new("help")
+            .alias("h")
+            .help("Show all commands or the help of one.")
+            .arg(Arg::positional("command", ValueType::String))
+            .example("help ban")
+            .handler(|_, invocation| Action::Help(invocation.args.positional(0).unwrap_or_default())))
+        .command(Command::new("exit").alias("q").help("Leave the REPL.").handler(|_, _| Action::Exit))
+        .command(Command::new("ban")
+            .help("Check the arguments of a ban and print them.")
+            .arg(Arg::positional("user", ValueType::Int).required().help("The user to ban."))
+            .arg(Arg::keyword("reason", ValueType::String).alias("r").required())
+            .arg(Arg::keyword("chats", ValueType::List(Box::new(ValueType::Int))).help("Only ban in these chats."))
+            .arg(Arg::keyword("duration", ValueType::Duration).help("Unban again after this long."))
+            .arg(Arg::flag("silent").help("Do not send a message."))
+            .example("ban 777000 reason: \"spam bot\" -silent")
+            .example("ban 777000 r: spam chats: [-1001129887931] duration: 1w")
+            .handler(|_, invocation| {
+                println!("{}", invocation.args);
+                Action::Done
+            }))
+}
+
+/// Completes command names, keywords, flags, allowed values and closing quotes and brackets on tab.
+struct CommandCompleter(Arc<CommandRegistry<(), Action>>);
 
-use marin::Marin;
-use pest::error::{Error, ErrorVariant, InputLocation};
-use std::collections::HashMap;
+impl<Term: Terminal> Completer<Term> for CommandCompleter {
+    fn complete(&self, _word: &str, prompter: &Prompter<Term>, start: usize, end: usize) -> Option<Vec<Completion>> {
+        let completions = self.0.complete(prompter.buffer(), end);
+        let completions = completions.into_iter().filter(|completion| completion.span.start == start);
+        Some(completions.map(|completion| Completion {
+                            completion: completion.replacement,
+                            display: None,
+                            suffix: Suffix::None,
+                        })
+                        .collect())
+    }
+
+    fn word_start(&self, line: &str, end: usize, _prompter: &Prompter<Term>) -> usize {
+        self.0.complete(line, end).first().map_or(end, |completion| completion.span.start)
+    }
+}
 
 fn main() -> io::Result<()> {
     let interface = Arc::new(Interface::new(env!("CARGO_PKG_NAME"))?);
+    let commands = Arc::new(commands());
+    interface.set_completer(Arc::new(CommandCompleter(commands.clone())));
 
-    println!("Enter \"help\" for examples.");
+    println!("Enter \"help\" for commands, anything else is parsed and printed, e.g.");
+    println!("  1 2 kw: \"string with spaces\" -flag range: 1..10 vals: [1, 2, 3]");
+    println!("Press Tab to complete commands and their arguments.");
     println!("Press Ctrl-D or enter \"exit\" to exit.");
-    println!("(Parsing errors are not formatted nicely right now)");
     println!();
 
     interface.set_prompt("marin> ")?;
 
     while let ReadResult::Input(line) = interface.read_line()? {
-        let (cmd, _) = split_first_word(&line);
-
-        match cmd {
-            "h" | "help" => {
-                println!("Examples:");
-                println!("  Positonal Arguments");
-                println!("    1 2 3");
-                println!("    arg1 arg2 arg3");
-                println!("  Keyword Arguments");
-                println!("    kw: 1");
-                println!("    keywordarg: True");
-                println!("  Flags");
-                println!("    -flag1 -flag2");
-                println!("  Quoted Strings");
-                println!("    kw: \"string with spaces\"");
-                println!("    \"positional argument with spaces\"");
-                println!("  Ranges");
-                println!("    range: 1..10");
-                println!("    1..10");
-                println!("    ..10");
-                println!("    ids: -10..20");
-                println!("  Lists");
-                println!("    vals: [\"val1\", \"val2\"]");
-                println!("    vals: [1, 2, 3]");
-                println!("    [1,2,3]");
-                println!();
+        if line.trim().is_empty() {
+            continue;
+        }
+        let command = line.split_whitespace().next().unwrap_or_default();
+        if commands.command_named(command).is_some() {
+            match commands.dispatch(&mut (), &line) {
+                Ok(Action::Help(None)) => println!("{}", commands.help(HelpFormat::Plain)),
+                Ok(Action::Help(Some(name))) => match commands.command_help(&name, HelpFormat::Plain) {
+                    Some(help) => println!("{}", help),
+                    None => println!("unknown command `{}`", name),
+                },
+                Ok(Action::Exit) => break,
+                Ok(Action::Done) => (),
+                Err(e) => print_error(&line, &e),
             }
-            "q" | "exit" => break,
-            _ => {
-                if !line.trim().is_empty() {
-                    match Marin::parse(&line) {
-                        Ok(r) => {
-                            println!("{}", r);
-                            println!()
-                        }
-                        Err(e) => println!("{}", e),
-                    }
+        } else {
+            match Marin::parse(&line) {
+                Ok(r) => {
+                    println!("{:#?}", r);
+                    println!("Canonical: {}", r);
                 }
+                Err(e) => print_error(&line, &e),
             }
         }
+        println!();
     }
 
     Ok(())
 }
 
-fn split_first_word(s: &str) -> (&str, &str) {
-    let s = s.trim();
-
-    match s.find(|ch: char| ch.is_whitespace()) {
-        Some(pos) => (&s[..pos], s[pos..].trim_start()),
-        None => (s, ""),
+fn print_error(line: &str, error: &Error) {
+    match (error, error.to_diagnostic(line)) {
+        (Error::Arguments(errors), Some(_)) => errors.iter().for_each(|e| println!("{}", e.to_diagnostic(line))),
+        (_, Some(d)) => println!("{}", d),
+        (_, None) => println!("{}", error),
     }
 }
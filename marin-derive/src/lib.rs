@@ -0,0 +1,252 @@
+//! `#[derive(MarinArgs)]` for the [marin](https://crates.io/crates/marin) crate.
+//!
+//! Every named field of the struct becomes an argument:
+//!
+//! * plain fields are required keyword arguments, `Option<T>` fields are optional
+//! * `#[marin(positional)]` takes the next positional argument instead
+//! * `#[marin(flag)]` marks a `bool` that is set with `-name` and unset with `-no-name`
+//! * `#[marin(alias = "r")]` accepts another keyword, may be repeated
+//! * `#[marin(default)]` and `#[marin(default = expr)]` make the argument optional
+//!
+//! Doc comments on the struct and its fields are used as help text.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Expr, ExprLit, ExprUnary, Fields, GenericArgument, Lit,
+          LitStr, Meta, PathArguments, Type, UnOp};
+
+#[proc_macro_derive(MarinArgs, attributes(marin))]
+pub fn derive_marin_args(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+enum Default {
+    Trait,
+    Expr(Expr),
+}
+
+struct Field {
+    ident: syn::Ident,
+    name: String,
+    ty: Type,
+    positional: bool,
+    flag: bool,
+    aliases: Vec<LitStr>,
+    default: Option<Default>,
+    help: String,
+}
+
+impl Field {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let ident = field.ident.clone().expect("named field");
+        let mut parsed = Field {
+            name: ident.to_string().trim_start_matches("r#").to_string(),
+            ident,
+            ty: field.ty.clone(),
+            positional: false,
+            flag: false,
+            aliases: vec![],
+            default: None,
+            help: doc_comment(&field.attrs),
+        };
+
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("marin")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("positional") {
+                    parsed.positional = true;
+                } else if meta.path.is_ident("flag") {
+                    parsed.flag = true;
+                } else if meta.path.is_ident("alias") {
+                    parsed.aliases.push(meta.value()?.parse()?);
+                } else if meta.path.is_ident("default") {
+                    parsed.default = Some(match meta.value() {
+                        Ok(value) => Default::Expr(value.parse()?),
+                        Err(_) => Default::Trait,
+                    });
+                } else {
+                    return Err(meta.error("expected `positional`, `flag`, `alias` or `default`"));
+                }
+                Ok(())
+            })?;
+        }
+
+        if parsed.positional && (parsed.flag || !parsed.aliases.is_empty()) {
+            return Err(syn::Error::new_spanned(&field.ty, "positional arguments can not be flags or have aliases"));
+        }
+        Ok(parsed)
+    }
+
+    /// The `T` of an `Option<T>` field.
+    fn option_inner(&self) -> Option<&Type> {
+        let path = match &self.ty {
+            Type::Path(path) if path.qself.is_none() => &path.path,
+            _ => return None,
+        };
+        let segment = path.segments.last()?;
+        if segment.ident != "Option" {
+            return None;
+        }
+        match &segment.arguments {
+            PathArguments::AngleBracketed(args) if args.args.len() == 1 => match args.args.first()? {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// An expression of type `Option<field type>` that reads the field.
+    fn read(&self) -> TokenStream2 {
+        let name = &self.name;
+        let aliases = &self.aliases;
+        let ty = &self.ty;
+        let (optional, required) = if self.positional {
+            (quote!(optional_positional), quote!(required_positional))
+        } else {
+            (quote!(optional), quote!(required))
+        };
+        let args = if self.positional {
+            quote!(#name)
+        } else {
+            quote!(#name, &[#(#aliases),*])
+        };
+
+        if let Some(inner) = self.option_inner() {
+            return quote!(Some(reader.#optional::<#inner>(#args)));
+        }
+        match &self.default {
+            Some(Default::Trait) => quote!(Some(reader.#optional::<#ty>(#args).unwrap_or_default())),
+            Some(Default::Expr(expr)) => {
+                let expr = default_expr(expr);
+                quote!(Some(reader.#optional::<#ty>(#args).unwrap_or_else(|| #expr)))
+            }
+            None if self.flag => quote!(Some(reader.flag(#args))),
+            None => quote!(reader.#required::<#ty>(#args)),
+        }
+    }
+
+    /// An expression building the `marin::Arg` describing the field.
+    fn arg(&self) -> TokenStream2 {
+        let name = &self.name;
+        let aliases = &self.aliases;
+        let help = &self.help;
+        let value_ty = self.option_inner().unwrap_or(&self.ty);
+        let value_type = quote!(<#value_ty as ::marin::FromMarinValue>::value_type());
+        let mut arg = if self.positional {
+            quote!(::marin::Arg::positional(#name, #value_type))
+        } else if self.flag {
+            quote!(::marin::Arg::flag(#name))
+        } else {
+            quote!(::marin::Arg::keyword(#name, #value_type))
+        };
+        arg.extend(quote!(#(.alias(#aliases))*));
+        if self.option_inner().is_none() && self.default.is_none() && !self.flag {
+            arg.extend(quote!(.required()));
+        }
+        if let Some(Default::Expr(expr)) = &self.default {
+            if let Some(value) = literal_value(expr) {
+                arg.extend(quote!(.default(#value)));
+            }
+        }
+        if !help.is_empty() {
+            arg.extend(quote!(.help(#help)));
+        }
+        arg
+    }
+}
+
+/// A `marin::MarinValue` for literal defaults, other expressions are not known until runtime.
+fn literal_value(expr: &Expr) -> Option<TokenStream2> {
+    match expr {
+        Expr::Lit(ExprLit { lit, .. }) => match lit {
+            Lit::Str(s) => Some(quote!(::marin::MarinValue::String(#s.into()))),
+            Lit::Int(i) => Some(quote!(::marin::MarinValue::Int(#i))),
+            Lit::Float(f) => Some(quote!(::marin::MarinValue::Float(#f))),
+            Lit::Bool(b) => Some(quote!(::marin::MarinValue::Bool(#b))),
+            _ => None,
+        },
+        Expr::Unary(ExprUnary { op: UnOp::Neg(_), expr: inner, .. }) => match &**inner {
+            Expr::Lit(ExprLit { lit: Lit::Int(i), .. }) => Some(quote!(::marin::MarinValue::Int(-#i))),
+            Expr::Lit(ExprLit { lit: Lit::Float(f), .. }) => Some(quote!(::marin::MarinValue::Float(-#f))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// String literals are converted with `From` so `default = "spam"` works for `String` fields.
+fn default_expr(expr: &Expr) -> TokenStream2 {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Str(_), .. }) => quote!(::std::convert::From::from(#expr)),
+        _ => quote!(#expr),
+    }
+}
+
+fn doc_comment(attrs: &[Attribute]) -> String {
+    let lines: Vec<String> = attrs.iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    lines.join(" ").trim().to_string()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "MarinArgs requires named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "MarinArgs can only be derived for structs")),
+    };
+    let fields = fields.iter().map(Field::parse).collect::<syn::Result<Vec<_>>>()?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let description = doc_comment(&input.attrs);
+    let arguments = fields.iter().map(Field::arg);
+
+    let names: Vec<_> = fields.iter().map(|f| &f.ident).collect();
+    let vars: Vec<_> = fields.iter().map(|f| format_ident!("__{}", f.name)).collect();
+    let reads = fields.iter().map(Field::read);
+    let build = if fields.is_empty() {
+        quote!(reader.finish().map(|()| #ident {}))
+    } else {
+        quote! {
+            match (#(#vars,)*) {
+                (#(Some(#vars),)*) => reader.finish().map(|()| #ident { #(#names: #vars),* }),
+                _ => Err(reader.finish().err().unwrap_or_default()),
+            }
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::marin::MarinArgs for #ident #ty_generics #where_clause {
+            fn from_marin(marin: &::marin::Marin) -> ::std::result::Result<Self, ::std::vec::Vec<::marin::ArgError>> {
+                let mut reader = ::marin::ArgReader::new(marin);
+                #(let #vars = #reads;)*
+                #build
+            }
+
+            fn arguments() -> ::std::vec::Vec<::marin::Arg> {
+                vec![#(#arguments),*]
+            }
+
+            fn description() -> &'static str {
+                #description
+            }
+        }
+    })
+}
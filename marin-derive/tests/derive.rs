@@ -0,0 +1,154 @@
+use std::ops::Range;
+
+use marin::{
+    ArgError, ArgErrorKind, ArgKind, Command, CommandRegistry, Error, Marin, MarinArgs, MarinValue, ValueKind, ValueType,
+};
+
+/// Ban a user in all chats.
+#[derive(Debug, MarinArgs, PartialEq)]
+struct BanArgs {
+    /// The user to ban.
+    #[marin(positional)]
+    user: i64,
+    /// Why the user is banned.
+    #[marin(alias = "r")]
+    reason: String,
+    #[marin(default)]
+    chats: Vec<i64>,
+    /// Do not send a message.
+    #[marin(flag)]
+    silent: bool,
+    #[marin(default = 100)]
+    limit: u32,
+    #[marin(default = "spam")]
+    category: String,
+    ids: Option<Range<i64>>,
+}
+
+#[test]
+fn all_arguments() {
+    let args = BanArgs::parse_args("777000 r: \"spam bot\" chats: [-1001129887931] -silent limit: 5 \
+                                    category: scam ids: 1..10").unwrap();
+    assert_eq!(args, BanArgs {
+        user: 777000,
+        reason: "spam bot".into(),
+        chats: vec![-1001129887931],
+        silent: true,
+        limit: 5,
+        category: "scam".into(),
+        ids: Some(1..10),
+    });
+}
+
+#[test]
+fn defaults() {
+    let args = BanArgs::parse_args("777000 reason: spam").unwrap();
+    assert_eq!(args, BanArgs {
+        user: 777000,
+        reason: "spam".into(),
+        chats: vec![],
+        silent: false,
+        limit: 100,
+        category: "spam".into(),
+        ids: None,
+    });
+}
+
+#[test]
+fn every_error_is_reported() {
+    let errors = match BanArgs::parse_args("\"not an id\" chats: [1, \"two\"] extra -loud") {
+        Err(Error::Arguments(errors)) => errors,
+        other => panic!("expected argument errors, got {:?}", other),
+    };
+    assert_eq!(errors, vec![
+        ArgError::new("user", ArgErrorKind::Mistyped { expected: "int".into(), found: ValueKind::String }),
+        ArgError::new("reason", ArgErrorKind::Missing),
+        ArgError::new("chats", ArgErrorKind::Mistyped { expected: "list of ints".into(), found: ValueKind::List }),
+        ArgError::new("#2", ArgErrorKind::Unexpected),
+        ArgError::new("loud", ArgErrorKind::Unexpected),
+    ]);
+    assert_eq!(errors[2].to_string(), "`chats`: expected list of ints, found list");
+}
+
+#[test]
+fn metadata() {
+    assert_eq!(BanArgs::description(), "Ban a user in all chats.");
+    let arguments = BanArgs::arguments();
+    let names: Vec<_> = arguments.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(names, vec!["user", "reason", "chats", "silent", "limit", "category", "ids"]);
+
+    assert_eq!(arguments[0].kind, ArgKind::Positional);
+    assert_eq!(arguments[0].help, "The user to ban.");
+    assert!(arguments[0].required);
+    assert_eq!(arguments[1].aliases, vec!["r"]);
+    assert_eq!(arguments[3].kind, ArgKind::Flag);
+    assert!(!arguments[3].required);
+    assert_eq!(arguments[2].value_type, ValueType::List(Box::new(ValueType::Int)));
+    assert_eq!(arguments[4].default, Some(MarinValue::Int(100)));
+    assert_eq!(arguments[5].default, Some("spam".into()));
+    assert_eq!(arguments[6].value_type, ValueType::Range);
+    assert!(!arguments[6].required);
+}
+
+#[test]
+fn runtime_schema() {
+    let schema = BanArgs::schema();
+    assert!(schema.validate(&Marin::parse("777000 r: spam -silent").unwrap()).is_ok());
+    let errors = schema.validate(&Marin::parse("777000 chats: [\"a\"]").unwrap()).unwrap_err();
+    assert_eq!(errors.len(), 2);
+}
+
+#[test]
+fn empty_struct() {
+    #[derive(MarinArgs)]
+    struct NoArgs {}
+
+    assert!(NoArgs::parse_args("").is_ok());
+    assert!(NoArgs::parse_args("1").is_err());
+}
+
+#[test]
+fn unknown_keywords_keep_their_order() {
+    #[derive(Debug, MarinArgs)]
+    struct NoArgs {}
+
+    let errors = match NoArgs::parse_args("zeta: 1 alpha: 2 -mid") {
+        Err(Error::Arguments(errors)) => errors,
+        other => panic!("expected argument errors, got {:?}", other),
+    };
+    assert_eq!(errors, vec![
+        ArgError::new("zeta", ArgErrorKind::Unexpected),
+        ArgError::new("alpha", ArgErrorKind::Unexpected),
+        ArgError::new("mid", ArgErrorKind::Unexpected),
+    ]);
+}
+
+#[derive(Debug, MarinArgs, PartialEq)]
+struct NotifyArgs {
+    #[marin(flag, default = true)]
+    mention: bool,
+    #[marin(flag)]
+    pin: Option<bool>,
+}
+
+#[test]
+fn flags_in_both_directions() {
+    assert_eq!(NotifyArgs::parse_args("").unwrap(), NotifyArgs { mention: true, pin: None });
+    assert_eq!(NotifyArgs::parse_args("-no-mention -pin").unwrap(), NotifyArgs { mention: false, pin: Some(true) });
+    assert_eq!(NotifyArgs::parse_args("!pin").unwrap(), NotifyArgs { mention: true, pin: Some(false) });
+    assert_eq!(NotifyArgs::arguments()[0].default, Some(MarinValue::Bool(true)));
+}
+
+#[test]
+fn commands() {
+    let registry = CommandRegistry::new().command(Command::new("ban").args::<BanArgs>().handler(
+        |banned: &mut Vec<BanArgs>, invocation| banned.push(invocation.parse().unwrap()),
+    ));
+    assert_eq!(registry.commands()[0].help, "Ban a user in all chats.");
+
+    let mut banned = vec![];
+    registry.dispatch(&mut banned, "ban 777000 reason: spam").unwrap();
+    assert_eq!(banned[0].user, 777000);
+    assert_eq!(banned[0].limit, 100);
+    assert!(matches!(registry.dispatch(&mut banned, "ban reason: spam"), Err(Error::Arguments(_))));
+}
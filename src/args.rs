@@ -0,0 +1,507 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::{error, fmt};
+use std::ops::{Range, RangeFrom, RangeInclusive, RangeTo, RangeToInclusive};
+use std::time::Duration;
+
+use indexmap::IndexMap;
+
+use crate::diagnostic::Diagnostic;
+use crate::error::{Error, Result};
+use crate::marin_value::{MarinValue, ValueKind, ValueType};
+use crate::range::MarinRange;
+use crate::schema::{Arg, Schema};
+use crate::span::Span;
+use crate::Marin;
+
+/// A type that can be built from parsed arguments, usually implemented with `#[derive(MarinArgs)]`.
+pub trait MarinArgs: Sized {
+    /// Convert `marin`, reporting every missing, unexpected and mistyped argument.
+    fn from_marin(marin: &Marin) -> std::result::Result<Self, Vec<ArgError>>;
+
+    /// Descriptions of the accepted arguments in declaration order.
+    fn arguments() -> Vec<Arg>;
+
+    /// A runtime [`Schema`] accepting the same arguments.
+    fn schema() -> Schema {
+        Self::arguments().into_iter().fold(Schema::new(), Schema::arg)
+    }
+
+    /// Help text for the whole command, taken from the doc comment of the struct.
+    fn description() -> &'static str {
+        ""
+    }
+
+    /// Parse `string` and convert the result.
+    fn parse_args(string: &str) -> Result<Self> {
+        Self::from_marin(&Marin::parse(string)?).map_err(Error::Arguments)
+    }
+}
+
+/// Conversion from a single [`MarinValue`].
+pub trait FromMarinValue: Sized {
+    fn value_type() -> ValueType;
+
+    /// What the value is described as in error messages, e.g. `int` or `list of ints`.
+    fn expected() -> String {
+        Self::value_type().to_string()
+    }
+
+    /// `None` if `value` has the wrong kind or does not fit into `Self`.
+    fn from_marin_value(value: &MarinValue) -> Option<Self>;
+}
+
+impl FromMarinValue for MarinValue {
+    fn value_type() -> ValueType {
+        ValueType::Any
+    }
+
+    fn from_marin_value(value: &MarinValue) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+/// `null` converts to `None`, so an explicitly cleared value can be told apart from a missing one.
+impl<T: FromMarinValue> FromMarinValue for Option<T> {
+    fn value_type() -> ValueType {
+        T::value_type()
+    }
+
+    fn expected() -> String {
+        format!("{} or null", T::expected())
+    }
+
+    fn from_marin_value(value: &MarinValue) -> Option<Self> {
+        match value {
+            MarinValue::Null => Some(None),
+            value => T::from_marin_value(value).map(Some),
+        }
+    }
+}
+
+impl FromMarinValue for String {
+    fn value_type() -> ValueType {
+        ValueType::String
+    }
+
+    fn from_marin_value(value: &MarinValue) -> Option<Self> {
+        match value {
+            MarinValue::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromMarinValue for bool {
+    fn value_type() -> ValueType {
+        ValueType::Bool
+    }
+
+    fn from_marin_value(value: &MarinValue) -> Option<Self> {
+        match value {
+            MarinValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_from_marin_value_int {
+    ($($t:ty),*) => {
+        $(
+            impl FromMarinValue for $t {
+                fn value_type() -> ValueType {
+                    ValueType::Int
+                }
+
+                fn expected() -> String {
+                    int_range(i128::from(<$t>::MIN as i64), <$t>::MAX as i128)
+                }
+
+                fn from_marin_value(value: &MarinValue) -> Option<Self> {
+                    match value {
+                        MarinValue::Int(i) => std::convert::TryFrom::try_from(*i).ok(),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_marin_value_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+/// Describes the ints between `min` and `max` that fit into an `i64`.
+fn int_range(min: i128, max: i128) -> String {
+    match (min, max >= i128::from(i64::MAX)) {
+        (0, true) => "non-negative int".into(),
+        (_, true) => "int".into(),
+        (min, false) => format!("int between {} and {}", min, max),
+    }
+}
+
+/// `expected` with its first word in plural, like `ints between 0 and 255`.
+fn plural(expected: &str) -> String {
+    match expected.find(' ') {
+        Some(i) => format!("{}s{}", &expected[..i], &expected[i..]),
+        None => format!("{}s", expected),
+    }
+}
+
+impl FromMarinValue for f64 {
+    fn value_type() -> ValueType {
+        ValueType::Float
+    }
+
+    fn from_marin_value(value: &MarinValue) -> Option<Self> {
+        match value {
+            MarinValue::Float(f) => Some(*f),
+            MarinValue::Int(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+}
+
+impl FromMarinValue for f32 {
+    fn value_type() -> ValueType {
+        ValueType::Float
+    }
+
+    fn from_marin_value(value: &MarinValue) -> Option<Self> {
+        f64::from_marin_value(value).map(|f| f as f32)
+    }
+}
+
+impl<T: FromMarinValue> FromMarinValue for Vec<T> {
+    fn value_type() -> ValueType {
+        ValueType::List(Box::new(T::value_type()))
+    }
+
+    fn expected() -> String {
+        format!("list of {}", plural(&T::expected()))
+    }
+
+    fn from_marin_value(value: &MarinValue) -> Option<Self> {
+        match value {
+            MarinValue::List(list) => list.iter().map(T::from_marin_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_from_marin_value_map {
+    ($($map:ident),*) => {
+        $(
+            impl<T: FromMarinValue> FromMarinValue for $map<String, T> {
+                fn value_type() -> ValueType {
+                    ValueType::Map(Box::new(T::value_type()))
+                }
+
+                fn expected() -> String {
+                    format!("map of {}", plural(&T::expected()))
+                }
+
+                fn from_marin_value(value: &MarinValue) -> Option<Self> {
+                    match value {
+                        MarinValue::Map(map) => map.iter()
+                            .map(|(key, value)| Some((key.clone(), T::from_marin_value(value)?)))
+                            .collect(),
+                        _ => None,
+                    }
+                }
+            }
+
+            impl<T: FromMarinValue> TryFrom<&MarinValue> for $map<String, T> {
+                type Error = ConversionError;
+
+                fn try_from(value: &MarinValue) -> std::result::Result<Self, ConversionError> {
+                    convert(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_marin_value_map!(IndexMap, HashMap, BTreeMap);
+
+impl FromMarinValue for MarinRange {
+    fn value_type() -> ValueType {
+        ValueType::Range
+    }
+
+    fn from_marin_value(value: &MarinValue) -> Option<Self> {
+        match value {
+            MarinValue::Range(range) => Some(*range),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_from_marin_value_range {
+    ($($t:ty => $convert:ident, $expected:expr;)*) => {
+        $(
+            impl FromMarinValue for $t {
+                fn value_type() -> ValueType {
+                    ValueType::Range
+                }
+
+                fn expected() -> String {
+                    $expected.into()
+                }
+
+                fn from_marin_value(value: &MarinValue) -> Option<Self> {
+                    MarinRange::from_marin_value(value)?.$convert()
+                }
+            }
+        )*
+    };
+}
+
+impl_from_marin_value_range! {
+    Range<i64> => as_range, "range like `a..b`";
+    RangeInclusive<i64> => as_range_inclusive, "range like `a..=b`";
+    RangeFrom<i64> => as_range_from, "range like `a..`";
+    RangeTo<i64> => as_range_to, "range like `..b`";
+    RangeToInclusive<i64> => as_range_to_inclusive, "range like `..=b`";
+}
+
+macro_rules! impl_try_from {
+    ($($t:ty),*) => {
+        $(
+            impl TryFrom<&MarinValue> for $t {
+                type Error = ConversionError;
+
+                fn try_from(value: &MarinValue) -> std::result::Result<Self, ConversionError> {
+                    convert(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from!(String, bool, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64, Duration);
+impl_try_from!(MarinRange, Range<i64>, RangeInclusive<i64>, RangeFrom<i64>, RangeTo<i64>, RangeToInclusive<i64>);
+
+impl<T: FromMarinValue> TryFrom<&MarinValue> for Vec<T> {
+    type Error = ConversionError;
+
+    fn try_from(value: &MarinValue) -> std::result::Result<Self, ConversionError> {
+        convert(value)
+    }
+}
+
+/// Convert `value`, the error does not name a key yet.
+pub(crate) fn convert<T: FromMarinValue>(value: &MarinValue) -> std::result::Result<T, ConversionError> {
+    T::from_marin_value(value).ok_or_else(|| ConversionError { key: None, expected: T::expected(), found: value.kind() })
+}
+
+/// A value that could not be converted to the requested type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionError {
+    /// The keyword, or `#n` for the nth positional argument, if the value was looked up by it.
+    pub key: Option<String>,
+    pub expected: String,
+    pub found: ValueKind,
+}
+
+impl ConversionError {
+    pub(crate) fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(key) = &self.key {
+            write!(f, "`{}`: ", key)?;
+        }
+        write!(f, "expected {}, found {}", self.expected, self.found)
+    }
+}
+
+impl error::Error for ConversionError {}
+
+impl FromMarinValue for Duration {
+    fn value_type() -> ValueType {
+        ValueType::Duration
+    }
+
+    fn from_marin_value(value: &MarinValue) -> Option<Self> {
+        match value {
+            MarinValue::Duration(duration) => Some(*duration),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    Positional,
+    Keyword,
+    Flag,
+}
+
+/// A problem with a single argument.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgError {
+    /// The keyword, or the name of the positional slot.
+    pub argument: String,
+    pub kind: ArgErrorKind,
+    /// Where the argument was given, if known.
+    pub span: Option<Span>,
+    /// A known name close to an unexpected keyword or flag, like `reason` for `reson`.
+    pub suggestion: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgErrorKind {
+    Missing,
+    /// The argument is not accepted at all.
+    Unexpected,
+    Mistyped { expected: String, found: ValueKind },
+    /// The value is not one of the allowed values.
+    NotAllowed { allowed: Vec<MarinValue> },
+    /// A number, or a number in a list, is outside of the bounds.
+    OutOfRange { min: Option<f64>, max: Option<f64> },
+}
+
+impl ArgError {
+    pub fn new(argument: impl Into<String>, kind: ArgErrorKind) -> Self {
+        ArgError { argument: argument.into(), kind, span: None, suggestion: None }
+    }
+
+    /// A [`Diagnostic`] pointing into `input`, errors without a span like missing arguments point at its end.
+    pub fn to_diagnostic(&self, input: &str) -> Diagnostic {
+        let span = self.span.unwrap_or_else(|| Span::new(input.len(), input.len()));
+        Diagnostic::new(self.to_string(), span, input)
+    }
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ArgErrorKind::Missing => write!(f, "missing argument `{}`", self.argument),
+            ArgErrorKind::Unexpected => match &self.suggestion {
+                Some(known) => write!(f, "unexpected argument `{}`, did you mean `{}`?", self.argument, known),
+                None => write!(f, "unexpected argument `{}`", self.argument),
+            },
+            ArgErrorKind::Mistyped { expected, found } =>
+                write!(f, "`{}`: expected {}, found {}", self.argument, expected, found),
+            ArgErrorKind::NotAllowed { allowed } => {
+                let allowed: Vec<_> = allowed.iter().map(describe).collect();
+                write!(f, "`{}`: expected one of {}", self.argument, allowed.join(", "))
+            }
+            ArgErrorKind::OutOfRange { min: Some(min), max: Some(max) } =>
+                write!(f, "`{}`: expected a number between {} and {}", self.argument, min, max),
+            ArgErrorKind::OutOfRange { min: Some(min), max: None } =>
+                write!(f, "`{}`: expected a number of at least {}", self.argument, min),
+            ArgErrorKind::OutOfRange { min: None, max: Some(max) } =>
+                write!(f, "`{}`: expected a number of at most {}", self.argument, max),
+            ArgErrorKind::OutOfRange { min: None, max: None } => write!(f, "`{}`: number is out of range", self.argument),
+        }
+    }
+}
+
+/// Short rendering of an allowed value.
+fn describe(value: &MarinValue) -> String {
+    match value {
+        MarinValue::String(s) => s.clone(),
+        MarinValue::Bool(b) => b.to_string(),
+        MarinValue::Int(i) => i.to_string(),
+        MarinValue::Float(f) => f.to_string(),
+        MarinValue::Null => "null".into(),
+        MarinValue::Range(range) => range.to_string(),
+        MarinValue::Map(map) => {
+            let entries: Vec<_> = map.iter().map(|(key, value)| format!("{}: {}", key, describe(value))).collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        MarinValue::Duration(duration) => crate::marin_value::format_duration(*duration),
+        MarinValue::List(list) => format!("[{}]", list.iter().map(describe).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+/// Takes arguments out of a [`Marin`] one by one, collecting errors on the way.
+///
+/// This is what `#[derive(MarinArgs)]` expands to.
+pub struct ArgReader<'m, 'a> {
+    marin: &'m Marin<'a>,
+    position: usize,
+    used: HashSet<&'m str>,
+    errors: Vec<ArgError>,
+}
+
+impl<'m, 'a> ArgReader<'m, 'a> {
+    pub fn new(marin: &'m Marin<'a>) -> Self {
+        ArgReader { marin, position: 0, used: HashSet::new(), errors: vec![] }
+    }
+
+    /// The keyword argument `name` or one of its `aliases`, `None` if it is absent, `null` or mistyped.
+    pub fn optional<T: FromMarinValue>(&mut self, name: &str, aliases: &[&str]) -> Option<T> {
+        let mut found = None;
+        for (key, value) in self.marin.kwargs.iter() {
+            if key == name || aliases.contains(&key.as_ref()) {
+                self.used.insert(key);
+                found = found.or(Some(value));
+            }
+        }
+        found.filter(|value| **value != MarinValue::Null).and_then(|value| self.convert(name, value))
+    }
+
+    /// Like [`optional`](ArgReader::optional) but records an error if the argument is absent or `null`.
+    pub fn required<T: FromMarinValue>(&mut self, name: &str, aliases: &[&str]) -> Option<T> {
+        let present = self.marin.kwargs.iter()
+            .any(|(key, value)| (key == name || aliases.contains(&key.as_ref())) && *value != MarinValue::Null);
+        if !present {
+            self.errors.push(ArgError::new(name, ArgErrorKind::Missing));
+        }
+        self.optional(name, aliases)
+    }
+
+    /// Whether the flag `name` or one of its `aliases` is set.
+    pub fn flag(&mut self, name: &str, aliases: &[&str]) -> bool {
+        self.optional(name, aliases).unwrap_or(false)
+    }
+
+    /// The next positional argument, described as `name` in errors.
+    pub fn optional_positional<T: FromMarinValue>(&mut self, name: &str) -> Option<T> {
+        let value = self.marin.args.get(self.position)?;
+        self.position += 1;
+        self.convert(name, value)
+    }
+
+    /// Like [`optional_positional`](ArgReader::optional_positional) but records an error if it is absent.
+    pub fn required_positional<T: FromMarinValue>(&mut self, name: &str) -> Option<T> {
+        if self.position >= self.marin.args.len() {
+            self.errors.push(ArgError::new(name, ArgErrorKind::Missing));
+        }
+        self.optional_positional(name)
+    }
+
+    /// Report unused arguments and return every error found.
+    pub fn finish(mut self) -> std::result::Result<(), Vec<ArgError>> {
+        for index in self.position..self.marin.args.len() {
+            self.errors.push(ArgError::new(format!("#{}", index + 1), ArgErrorKind::Unexpected));
+        }
+        for key in self.marin.kwargs.keys() {
+            if !self.used.contains(key.as_ref()) {
+                self.errors.push(ArgError::new(key.as_ref(), ArgErrorKind::Unexpected));
+            }
+        }
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn convert<T: FromMarinValue>(&mut self, name: &str, value: &MarinValue) -> Option<T> {
+        let converted = T::from_marin_value(value);
+        if converted.is_none() {
+            self.errors.push(ArgError::new(name, ArgErrorKind::Mistyped {
+                expected: T::expected(),
+                found: value.kind(),
+            }));
+        }
+        converted
+    }
+}
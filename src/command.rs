@@ -0,0 +1,359 @@
+use std::fmt;
+
+use crate::args::MarinArgs;
+use crate::error::{Error, Result};
+use crate::options::ParseOptions;
+use crate::schema::{Arg, Schema};
+use crate::span::Span;
+use crate::warning::{self, Warning};
+use crate::Marin;
+
+type Handler<C, R> = Box<dyn Fn(&mut C, &Invocation) -> R + Send + Sync>;
+
+/// A named command with the arguments it accepts, its subcommands and the handler that runs it.
+pub struct Command<C, R = ()> {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub help: String,
+    pub schema: Schema,
+    pub subcommands: Vec<Command<C, R>>,
+    /// Lines that run this command, shown in its help.
+    pub examples: Vec<String>,
+    handler: Option<Handler<C, R>>,
+}
+
+impl<C, R> Command<C, R> {
+    pub fn new(name: impl Into<String>) -> Self {
+        Command {
+            name: name.into(),
+            aliases: vec![],
+            help: String::new(),
+            schema: Schema::new(),
+            subcommands: vec![],
+            examples: vec![],
+            handler: None,
+        }
+    }
+
+    pub fn alias(mut self, alias: impl Into<String>) -> Self {
+        self.aliases.push(alias.into());
+        self
+    }
+
+    pub fn help(mut self, help: impl Into<String>) -> Self {
+        self.help = help.into();
+        self
+    }
+
+    pub fn arg(mut self, arg: Arg) -> Self {
+        self.schema = self.schema.arg(arg);
+        self
+    }
+
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Accept the arguments of `T`, the help defaults to its description.
+    pub fn args<T: MarinArgs>(mut self) -> Self {
+        self.schema = T::schema();
+        if self.help.is_empty() {
+            self.help = T::description().into();
+        }
+        self
+    }
+
+    pub fn subcommand(mut self, command: Command<C, R>) -> Self {
+        self.subcommands.push(command);
+        self
+    }
+
+    pub fn example(mut self, line: impl Into<String>) -> Self {
+        self.examples.push(line.into());
+        self
+    }
+
+    pub fn handler(mut self, handler: impl Fn(&mut C, &Invocation) -> R + Send + Sync + 'static) -> Self {
+        self.handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Whether `name` refers to this command.
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().any(|alias| alias == name)
+    }
+
+    /// The subcommand called `name`, aliases included.
+    pub fn subcommand_named(&self, name: &str) -> Option<&Command<C, R>> {
+        self.subcommands.iter().find(|command| command.is_named(name))
+    }
+
+    pub fn has_handler(&self) -> bool {
+        self.handler.is_some()
+    }
+}
+
+impl<C, R> fmt::Debug for Command<C, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Command")
+         .field("name", &self.name)
+         .field("aliases", &self.aliases)
+         .field("help", &self.help)
+         .field("schema", &self.schema)
+         .field("subcommands", &self.subcommands)
+         .field("examples", &self.examples)
+         .field("handler", &self.handler.is_some())
+         .finish()
+    }
+}
+
+/// A line resolved by [`CommandRegistry::resolve`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation<'r> {
+    /// The registered names of the command and its subcommands, like `["gban", "add"]`.
+    pub path: Vec<&'r str>,
+    /// The arguments after the command words, with the defaults of the schema applied.
+    pub args: Marin<'r>,
+    pub warnings: Vec<Warning>,
+    /// Where the command words are in the line.
+    pub span: Span,
+}
+
+impl Invocation<'_> {
+    /// Convert the arguments into `T`.
+    pub fn parse<T: MarinArgs>(&self) -> Result<T> {
+        T::from_marin(&self.args).map_err(Error::Arguments)
+    }
+}
+
+/// Commands a bot understands, parsing whole lines like `gban add 777000 reason: spam` and running their handlers
+/// with a context of type `C`.
+///
+/// ```
+/// use marin::{Arg, Command, CommandRegistry, ValueType};
+///
+/// let registry = CommandRegistry::new()
+///     .command(Command::new("gban")
+///         .subcommand(Command::new("add")
+///             .arg(Arg::positional("user", ValueType::Int).required())
+///             .arg(Arg::keyword("reason", ValueType::String).default("spam"))
+///             .handler(|banned: &mut Vec<(i64, String)>, invocation| {
+///                 let user = invocation.args.positional(0).unwrap().unwrap();
+///                 let reason = invocation.args.get("reason").unwrap().unwrap();
+///                 banned.push((user, reason));
+///             })));
+///
+/// let mut banned = vec![];
+/// registry.dispatch(&mut banned, "gban add 777000").unwrap();
+/// assert_eq!(banned, vec![(777000, "spam".to_string())]);
+/// assert_eq!(registry.dispatch(&mut banned, "gban").unwrap_err().to_string(), "`gban` needs a subcommand, one of add");
+/// ```
+pub struct CommandRegistry<C, R = ()> {
+    commands: Vec<Command<C, R>>,
+    options: ParseOptions,
+}
+
+impl<C, R> Default for CommandRegistry<C, R> {
+    fn default() -> Self {
+        CommandRegistry { commands: vec![], options: ParseOptions::default() }
+    }
+}
+
+impl<C, R> fmt::Debug for CommandRegistry<C, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CommandRegistry").field("commands", &self.commands).field("options", &self.options).finish()
+    }
+}
+
+impl<C, R> CommandRegistry<C, R> {
+    pub fn new() -> Self {
+        CommandRegistry::default()
+    }
+
+    pub fn command(mut self, command: Command<C, R>) -> Self {
+        self.commands.push(command);
+        self
+    }
+
+    /// Options used to parse the arguments of every command.
+    pub fn options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// All commands in the order they were added.
+    pub fn commands(&self) -> &[Command<C, R>] {
+        &self.commands
+    }
+
+    /// The top level command called `name`, aliases included.
+    pub fn command_named(&self, name: &str) -> Option<&Command<C, R>> {
+        self.commands.iter().find(|command| command.is_named(name))
+    }
+
+    /// Find the command at the start of `line`, then parse the rest and check it against the command's schema.
+    ///
+    /// Spans in errors and warnings point into `line`.
+    pub fn resolve<'r>(&'r self, line: &str) -> Result<(&'r Command<C, R>, Invocation<'r>)> {
+        let mut words = words(line).into_iter();
+        let first = words.next().unwrap_or_else(|| Span::new(line.len(), line.len()));
+        let mut command = self.command_named(first.as_str(line)).ok_or_else(|| Error::UnknownCommand {
+            command: first.as_str(line).into(),
+            span: first,
+        })?;
+        let mut path = vec![command.name.as_str()];
+        let mut span = first;
+        for word in words {
+            match command.subcommand_named(word.as_str(line)) {
+                Some(subcommand) => {
+                    command = subcommand;
+                    path.push(command.name.as_str());
+                    span.end = word.end;
+                }
+                None => break,
+            }
+        }
+        if !command.has_handler() && !command.subcommands.is_empty() {
+            return Err(incomplete(command, &path, span));
+        }
+
+        // The command words are blanked out so spans of the arguments point into the whole line.
+        let mut text = line.to_string();
+        text.replace_range(..span.end, &" ".repeat(span.end));
+        let spanned = Marin::parse_spanned_with(&text, &self.options)?;
+        let options = ParseOptions { keywords: command.schema.keyword_names(), ..self.options.clone() };
+        let warnings = warning::check(&spanned, &options);
+        command.schema.validate_spanned(&spanned).map_err(Error::Arguments)?;
+        let mut args: Marin<'r> = spanned.into_marin(self.options.duplicates)?.into_owned();
+        command.schema.apply_defaults(&mut args);
+        Ok((command, Invocation { path, args, warnings, span }))
+    }
+
+    /// Resolve `line` and run the handler of its command.
+    pub fn dispatch(&self, context: &mut C, line: &str) -> Result<R> {
+        let (command, invocation) = self.resolve(line)?;
+        match &command.handler {
+            Some(handler) => Ok(handler(context, &invocation)),
+            None => Err(incomplete(command, &invocation.path, invocation.span)),
+        }
+    }
+}
+
+fn incomplete<C, R>(command: &Command<C, R>, path: &[&str], span: Span) -> Error {
+    Error::IncompleteCommand {
+        command: path.join(" "),
+        subcommands: command.subcommands.iter().map(|command| command.name.clone()).collect(),
+        span,
+    }
+}
+
+/// Spans of the whitespace separated words of `line`.
+fn words(line: &str) -> Vec<Span> {
+    let mut words = vec![];
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                words.push(Span::new(s, i));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    words.extend(start.map(|s| Span::new(s, line.len())));
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arg, ArgErrorKind, Command, CommandRegistry, Error, MarinValue, ValueType};
+
+    #[derive(Debug, Default)]
+    struct Context {
+        log: Vec<String>,
+    }
+
+    fn registry() -> CommandRegistry<Context, usize> {
+        let user = || Arg::positional("user", ValueType::Int).required();
+        CommandRegistry::new()
+            .command(Command::new("gban")
+                .alias("g")
+                .subcommand(Command::new("add")
+                    .arg(user())
+                    .arg(Arg::keyword("reason", ValueType::String).default("spam"))
+                    .arg(Arg::flag("silent"))
+                    .handler(|context: &mut Context, invocation| {
+                        context.log.push(format!("{} {}", invocation.path.join(" "), invocation.args));
+                        context.log.len()
+                    }))
+                .subcommand(Command::new("remove").alias("rm").arg(user()).handler(|context: &mut Context, invocation| {
+                    context.log.push(invocation.path.join(" "));
+                    context.log.len()
+                })))
+            .command(Command::new("ping").handler(|_, _| 0))
+            .command(Command::new("stats"))
+    }
+
+    #[test]
+    fn subcommands() {
+        let registry = registry();
+        let mut context = Context::default();
+        assert_eq!(registry.dispatch(&mut context, "gban add 777000 -silent").unwrap(), 1);
+        assert_eq!(registry.dispatch(&mut context, "  g rm 777000").unwrap(), 2);
+        assert_eq!(registry.dispatch(&mut context, "ping").unwrap(), 0);
+        assert_eq!(context.log, vec!["gban add 777000 -silent reason: spam", "gban remove"]);
+
+        let (command, invocation) = registry.resolve("g add 1 reason: flood").unwrap();
+        assert_eq!(command.name, "add");
+        assert_eq!(invocation.span.as_str("g add 1 reason: flood"), "g add");
+        assert_eq!(invocation.args.kwargs["reason"], MarinValue::from("flood"));
+    }
+
+    #[test]
+    fn command_errors() {
+        let registry = registry();
+        let mut context = Context::default();
+        let mut error = |line| registry.dispatch(&mut context, line).unwrap_err();
+        assert_eq!(error("unban 1").to_string(), "unknown command `unban`");
+        assert_eq!(error("").to_string(), "no command given");
+        assert_eq!(error("gban 1").to_string(), "`gban` needs a subcommand, one of add, remove");
+        assert_eq!(error("stats").to_string(), "`stats` can not be run on its own");
+        assert!(matches!(error("gban add"), Error::Arguments(errors) if errors[0].kind == ArgErrorKind::Missing));
+        assert!(registry.resolve("stats").is_ok());
+    }
+
+    #[test]
+    fn spans_point_into_the_line() {
+        let registry = registry();
+        let line = "gban add 777000 -loud";
+        match registry.resolve(line).unwrap_err() {
+            Error::Arguments(errors) => assert_eq!(errors[0].span.unwrap().as_str(line), "-loud"),
+            other => panic!("expected argument errors, got {:?}", other),
+        }
+        let diagnostic = registry.resolve(line).unwrap_err().to_diagnostic(line).unwrap();
+        assert_eq!((diagnostic.column, diagnostic.span.as_str(line)), (17, "-loud"));
+        let diagnostic = registry.resolve("gban add").unwrap_err().to_diagnostic("gban add").unwrap();
+        assert_eq!((diagnostic.message.as_str(), diagnostic.column), ("missing argument `user`", 9));
+        let line = "gban add 777000 reason: \"spam";
+        let diagnostic = registry.resolve(line).unwrap_err().to_diagnostic(line).unwrap();
+        assert_eq!(diagnostic.column, 25);
+        assert!(diagnostic.to_string().contains("\ngban add 777000 reason: \"spam\n"));
+        let error = registry.resolve("gban  unban").unwrap_err();
+        assert_eq!(error.span().unwrap().as_str("gban  unban"), "gban");
+    }
+
+    #[test]
+    fn misspelt_keywords() {
+        let registry = registry();
+        let line = "gban add 777000 reson: spam -slient";
+        let errors = match registry.resolve(line).unwrap_err() {
+            Error::Arguments(errors) => errors,
+            other => panic!("expected argument errors, got {:?}", other),
+        };
+        assert_eq!(errors[0].to_string(), "unexpected argument `reson`, did you mean `reason`?");
+        assert_eq!(errors[1].suggestion.as_deref(), Some("silent"));
+        assert!(!registry.resolve("gban rm 1 reason: spam").unwrap_err().to_string().contains("did you mean"));
+    }
+}
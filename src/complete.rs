@@ -0,0 +1,321 @@
+use std::collections::HashSet;
+
+use crate::args::ArgKind;
+use crate::command::CommandRegistry;
+use crate::marin_value::{MarinValue, ValueType};
+use crate::schema::{Arg, Schema};
+use crate::span::Span;
+
+/// A candidate for completing the input at the cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub kind: CompletionKind,
+    /// The text to put in place of `span`.
+    pub replacement: String,
+    /// The part of the input before the cursor that is replaced, empty if the replacement is inserted.
+    pub span: Span,
+    /// Help of the command or argument.
+    pub help: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// A command or subcommand name followed by a space.
+    Command,
+    /// A keyword name followed by `: `.
+    Keyword,
+    /// A flag like `-silent` or `-no-silent`.
+    Flag,
+    /// One of the allowed values of the keyword under the cursor.
+    Value,
+    /// The quotes and brackets that are still open.
+    Closing,
+}
+
+/// A word of the input, whitespace in strings, lists and maps does not end it.
+struct Word {
+    span: Span,
+    /// The quote and brackets still open at the end of the word.
+    quote: Option<char>,
+    brackets: Vec<char>,
+}
+
+impl Schema {
+    /// Completions for `input` with the cursor at byte `cursor`, the input after the cursor is ignored.
+    ///
+    /// Incomplete input like an unterminated string or list is fine.
+    pub fn complete(&self, input: &str, cursor: usize) -> Vec<Completion> {
+        complete_arguments(self, input, 0, clamp(input, cursor))
+    }
+}
+
+impl<C, R> CommandRegistry<C, R> {
+    /// Completions for a whole command line, command and subcommand names first, then the arguments of the command.
+    pub fn complete(&self, line: &str, cursor: usize) -> Vec<Completion> {
+        let cursor = clamp(line, cursor);
+        let words = words(line, 0, cursor);
+        let (current, done) = match words.split_last() {
+            Some((current, done)) => (current, done),
+            None => return vec![],
+        };
+        let prefix = current.span.as_str(line);
+        let command_completion = |name: &str, help: &str| Completion {
+            kind: CompletionKind::Command,
+            replacement: format!("{} ", name),
+            span: current.span,
+            help: help.into(),
+        };
+
+        let (first, rest) = match done.split_first() {
+            Some(split) => split,
+            None => {
+                let names = self.commands().iter().filter(|command| command.name.starts_with(prefix));
+                return names.map(|command| command_completion(&command.name, &command.help)).collect();
+            }
+        };
+        let mut command = match self.command_named(first.span.as_str(line)) {
+            Some(command) => command,
+            None => return vec![],
+        };
+        let mut arguments_start = first.span.end;
+        for word in rest {
+            match command.subcommand_named(word.span.as_str(line)) {
+                Some(subcommand) => {
+                    command = subcommand;
+                    arguments_start = word.span.end;
+                }
+                _ => return complete_arguments(&command.schema, line, arguments_start, cursor),
+            }
+        }
+        let mut completions: Vec<_> = command.subcommands
+                                             .iter()
+                                             .filter(|subcommand| subcommand.name.starts_with(prefix))
+                                             .map(|subcommand| command_completion(&subcommand.name, &subcommand.help))
+                                             .collect();
+        if command.has_handler() || command.subcommands.is_empty() {
+            completions.extend(complete_arguments(&command.schema, line, arguments_start, cursor));
+        }
+        completions
+    }
+}
+
+/// `cursor` moved back onto a char boundary of `input`.
+fn clamp(input: &str, cursor: usize) -> usize {
+    let mut cursor = cursor.min(input.len());
+    while !input.is_char_boundary(cursor) {
+        cursor -= 1;
+    }
+    cursor
+}
+
+/// The words of `input[start..end]`, the last one is the word at `end` and may be empty.
+fn words(input: &str, start: usize, end: usize) -> Vec<Word> {
+    let mut words = vec![];
+    let mut current: Option<Word> = None;
+    let mut escaped = false;
+    for (i, c) in input[start..end].char_indices().map(|(i, c)| (start + i, c)) {
+        if let Some(word) = current.as_mut().filter(|word| word.quote.is_some()) {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                c if Some(c) == word.quote => word.quote = None,
+                _ => (),
+            }
+            continue;
+        }
+        if c.is_whitespace() {
+            if let Some(word) = current.as_mut().filter(|word| word.brackets.is_empty()) {
+                word.span.end = i;
+                words.extend(current.take());
+            }
+            continue;
+        }
+        let word = current.get_or_insert_with(|| Word { span: Span::new(i, i), quote: None, brackets: vec![] });
+        match c {
+            '"' | '\'' => word.quote = Some(c),
+            '[' | '{' => word.brackets.push(c),
+            ']' | '}' => {
+                word.brackets.pop();
+            }
+            _ => (),
+        }
+    }
+    if let Some(word) = current.as_mut() {
+        word.span.end = end;
+    }
+    words.push(current.unwrap_or(Word { span: Span::new(end, end), quote: None, brackets: vec![] }));
+    words
+}
+
+fn complete_arguments(schema: &Schema, input: &str, start: usize, cursor: usize) -> Vec<Completion> {
+    let words = words(input, start, cursor);
+    let (current, done) = match words.split_last() {
+        Some(split) => split,
+        None => return vec![],
+    };
+    let text = current.span.as_str(input);
+    let at_cursor = Span::new(cursor, cursor);
+
+    if let Some(quote) = current.quote {
+        let mut closings = vec![quote.to_string()];
+        if !current.brackets.is_empty() {
+            closings.push(format!("{}{}", quote, closing(&current.brackets)));
+        }
+        return closings.into_iter().map(|replacement| closing_completion(replacement, at_cursor)).collect();
+    }
+    if !current.brackets.is_empty() {
+        return vec![closing_completion(closing(&current.brackets), at_cursor)];
+    }
+
+    // The value of `key: ` in the previous word, or of `key:` in this one.
+    let keyword = |key: &str| schema.keyword(key).filter(|arg| arg.kind == ArgKind::Keyword);
+    let previous = done.last().map(|word| word.span.as_str(input));
+    if let Some(arg) = previous.and_then(|word| word.strip_suffix(':')).and_then(keyword) {
+        return value_completions(arg, text, current.span);
+    }
+    if let Some((key, value)) = text.split_once(':') {
+        return match keyword(key) {
+            Some(arg) => value_completions(arg, value, Span::new(current.span.start + key.len() + 1, cursor)),
+            None => vec![],
+        };
+    }
+
+    let used = used_arguments(schema, input, done);
+    let unused = schema.arguments().iter().filter(|arg| !used.contains(arg.name.as_str()));
+    let mut completions = vec![];
+    for arg in unused {
+        let names = std::iter::once(&arg.name).chain(arg.aliases.iter());
+        let candidates: Vec<_> = match arg.kind {
+            ArgKind::Positional => continue,
+            ArgKind::Keyword => names.map(|name| (CompletionKind::Keyword, format!("{}: ", name))).collect(),
+            ArgKind::Flag if text.starts_with('!') => names.map(|name| (CompletionKind::Flag, format!("!{}", name))).collect(),
+            ArgKind::Flag => names.flat_map(|name| vec![format!("-{}", name), format!("-no-{}", name)])
+                                  .map(|flag| (CompletionKind::Flag, flag))
+                                  .collect(),
+        };
+        for (kind, replacement) in candidates {
+            // An empty word offers keywords and the plain flags, a started one everything it could become.
+            let offered = match text {
+                "" => !replacement.starts_with("-no-"),
+                _ => replacement.starts_with(text),
+            };
+            if offered {
+                completions.push(Completion { kind, replacement, span: current.span, help: arg.help.clone() });
+            }
+        }
+    }
+    completions
+}
+
+/// Names of the keyword and flag arguments given in `words`.
+fn used_arguments<'s>(schema: &'s Schema, input: &str, words: &[Word]) -> HashSet<&'s str> {
+    let mut used = HashSet::new();
+    for word in words {
+        let text = word.span.as_str(input);
+        let key = match text.split_once(':') {
+            Some((key, _)) => key,
+            None => text.strip_prefix("-no-").or_else(|| text.strip_prefix(['-', '!'])).unwrap_or(""),
+        };
+        used.extend(schema.keyword(key).map(|arg| arg.name.as_str()));
+    }
+    used
+}
+
+fn value_completions(arg: &Arg, prefix: &str, span: Span) -> Vec<Completion> {
+    let values = match (&arg.value_type, arg.allowed.is_empty()) {
+        (ValueType::Bool, true) => vec![MarinValue::Bool(true), MarinValue::Bool(false)],
+        (_, _) => arg.allowed.clone(),
+    };
+    values.iter()
+          .map(ToString::to_string)
+          .filter(|value| value.starts_with(prefix) || value.trim_start_matches('"').starts_with(prefix))
+          .map(|replacement| Completion { kind: CompletionKind::Value, replacement, span, help: arg.help.clone() })
+          .collect()
+}
+
+fn closing_completion(replacement: String, span: Span) -> Completion {
+    Completion { kind: CompletionKind::Closing, replacement, span, help: String::new() }
+}
+
+/// The brackets closing `open`, innermost first.
+fn closing(open: &[char]) -> String {
+    open.iter().rev().map(|bracket| if *bracket == '[' { ']' } else { '}' }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arg, Command, CommandRegistry, CompletionKind, Schema, ValueType};
+
+    fn schema() -> Schema {
+        Schema::new()
+            .arg(Arg::positional("user", ValueType::Int).required())
+            .arg(Arg::keyword("reason", ValueType::String).alias("r").help("Why the user is banned."))
+            .arg(Arg::keyword("mode", ValueType::String).allowed(vec!["ban", "kick", "mute time"]))
+            .arg(Arg::keyword("notify", ValueType::Bool))
+            .arg(Arg::keyword("chats", ValueType::List(Box::new(ValueType::Int))))
+            .arg(Arg::flag("silent"))
+    }
+
+    /// The replacements for the input with the cursor at `|`.
+    fn complete(input: &str) -> Vec<String> {
+        let cursor = input.find('|').unwrap();
+        let input = input.replace('|', "");
+        schema().complete(&input, cursor).into_iter().map(|c| c.replacement).collect()
+    }
+
+    #[test]
+    fn keywords_and_flags() {
+        assert_eq!(complete("777000 |"), vec!["reason: ", "r: ", "mode: ", "notify: ", "chats: ", "-silent"]);
+        assert_eq!(complete("777000 re|"), vec!["reason: "]);
+        assert_eq!(complete("777000 -s|"), vec!["-silent"]);
+        assert_eq!(complete("777000 -no|"), vec!["-no-silent"]);
+        assert_eq!(complete("777000 !|"), vec!["!silent"]);
+        assert_eq!(complete("r: spam -silent m|"), vec!["mode: "]);
+        assert_eq!(complete("r: spam -silent | chats: [1]"), vec!["mode: ", "notify: ", "chats: "]);
+
+        let completions = schema().complete("777000 rea", 10);
+        assert_eq!(completions[0].kind, CompletionKind::Keyword);
+        assert_eq!(completions[0].span.as_str("777000 rea"), "rea");
+        assert_eq!(completions[0].help, "Why the user is banned.");
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(complete("mode: |"), vec!["ban", "kick", "\"mute time\""]);
+        assert_eq!(complete("mode: k|"), vec!["kick"]);
+        assert_eq!(complete("mode:b|"), vec!["ban"]);
+        assert_eq!(complete("1 notify: |"), vec!["true", "false"]);
+        assert!(complete("reason: |").is_empty());
+        assert_eq!(schema().complete("mode:b", 6)[0].span.as_str("mode:b"), "b");
+    }
+
+    #[test]
+    fn closings() {
+        assert_eq!(complete("reason: \"spam bot|"), vec!["\""]);
+        assert_eq!(complete("chats: [1, 2|"), vec!["]"]);
+        assert_eq!(complete("chats: [[1], {a: 'x|"), vec!["'", "'}]"]);
+        assert_eq!(complete("reason: \"a\\\"|"), vec!["\""]);
+        assert_eq!(complete("reason: \"a b\" |"), vec!["mode: ", "notify: ", "chats: ", "-silent"]);
+    }
+
+    #[test]
+    fn commands() {
+        let registry: CommandRegistry<()> = CommandRegistry::new()
+            .command(Command::new("gban")
+                .subcommand(Command::new("add").schema(schema()).handler(|_, _| ()))
+                .subcommand(Command::new("remove").handler(|_, _| ())))
+            .command(Command::new("ping").help("Check the bot is alive.").handler(|_, _| ()));
+        let complete = |line: &str| -> Vec<String> {
+            registry.complete(line, line.len()).into_iter().map(|c| c.replacement).collect()
+        };
+        assert_eq!(complete(""), vec!["gban ", "ping "]);
+        assert_eq!(complete("p"), vec!["ping "]);
+        assert_eq!(complete("gban "), vec!["add ", "remove "]);
+        assert_eq!(complete("gban r"), vec!["remove "]);
+        assert_eq!(complete("gban add 1 -"), vec!["-silent", "-no-silent"]);
+        assert_eq!(complete("gban add 1 mode: m"), vec!["\"mute time\""]);
+        assert!(complete("unban ").is_empty());
+        assert_eq!(registry.complete("pi", 2)[0].help, "Check the bot is alive.");
+        assert_eq!(registry.complete("gban add", 100)[0].replacement, "add ");
+    }
+}
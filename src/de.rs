@@ -0,0 +1,363 @@
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::{forward_to_deserialize_any, Deserialize};
+
+use crate::error::{Error, Result};
+use crate::marin_value::MarinValue;
+use crate::Marin;
+
+/// Deserialize `T` from the keyword arguments of `marin`, positional arguments are
+/// passed as a list in the field `args`.
+pub fn from_marin<'de, T: Deserialize<'de>>(marin: &'de Marin) -> Result<T> {
+    T::deserialize(Deserializer::new(marin))
+}
+
+/// Parse `string` and deserialize `T` from it, see [`from_marin`].
+pub fn from_str<T: DeserializeOwned>(string: &str) -> Result<T> {
+    from_marin(&Marin::parse(string)?)
+}
+
+/// Deserializes a parsed [`Marin`] as a map of its keyword arguments.
+///
+/// Positional arguments are exposed under the field set with
+/// [`positional_field`](Deserializer::positional_field), `args` by default.
+/// Deserializing a sequence or tuple yields the positional arguments only.
+pub struct Deserializer<'de, 'a> {
+    marin: &'de Marin<'a>,
+    positional: &'static str,
+}
+
+impl<'de, 'a> Deserializer<'de, 'a> {
+    pub fn new(marin: &'de Marin<'a>) -> Self {
+        Deserializer { marin, positional: "args" }
+    }
+
+    /// Name of the field that receives the positional arguments.
+    pub fn positional_field(mut self, name: &'static str) -> Self {
+        self.positional = name;
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de, '_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut entries: Vec<(&'de str, Entry<'de>)> = self.marin.kwargs.iter()
+            .map(|(key, value)| (key.as_ref(), Entry::Value(value)))
+            .collect();
+        if !self.marin.args.is_empty() {
+            entries.push((self.positional, Entry::Positional(&self.marin.args)));
+        }
+        visitor.visit_map(KeywordAccess { entries: entries.into_iter(), key: None, value: None })
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(SeqDeserializer::new(self.marin.args.iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V)
+                                                 -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+enum Entry<'de> {
+    Value(&'de MarinValue),
+    Positional(&'de [MarinValue]),
+}
+
+struct KeywordAccess<'de> {
+    entries: std::vec::IntoIter<(&'de str, Entry<'de>)>,
+    key: Option<&'de str>,
+    value: Option<Entry<'de>>,
+}
+
+impl<'de> MapAccess<'de> for KeywordAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.key = Some(key);
+                self.value = Some(value);
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let result = match self.value.take() {
+            Some(Entry::Value(value)) => seed.deserialize(ValueDeserializer(value)),
+            Some(Entry::Positional(args)) => seed.deserialize(SeqDeserializer::new(args.iter())),
+            None => Err(de::Error::custom("value requested before key")),
+        };
+        result.map_err(|e| match e {
+            Error::Deserialize { key: None, message } => Error::Deserialize {
+                key: self.key.map(Into::into),
+                message,
+            },
+            e => e,
+        })
+    }
+}
+
+/// Deserializes a single [`MarinValue`].
+pub struct ValueDeserializer<'de>(pub &'de MarinValue);
+
+impl<'de> IntoDeserializer<'de, Error> for &'de MarinValue {
+    type Deserializer = ValueDeserializer<'de>;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        ValueDeserializer(self)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            MarinValue::String(s) => visitor.visit_borrowed_str(s),
+            MarinValue::Bool(b) => visitor.visit_bool(*b),
+            MarinValue::Int(i) => visitor.visit_i64(*i),
+            MarinValue::Float(f) => visitor.visit_f64(*f),
+            MarinValue::Null => visitor.visit_unit(),
+            MarinValue::List(list) => visitor.visit_seq(SeqDeserializer::new(list.iter())),
+            MarinValue::Range(range) => {
+                let fields = vec![("start", range.start()), ("end", range.end()), ("step", range.step())];
+                let fields = fields.into_iter().filter_map(|(key, value)| Some((key, value?)));
+                visitor.visit_map(MapDeserializer::new(fields))
+            }
+            MarinValue::Map(map) =>
+                visitor.visit_map(MapDeserializer::new(map.iter().map(|(key, value)| (key.as_str(), value)))),
+            MarinValue::Duration(duration) => {
+                let fields = vec![("secs", duration.as_secs()), ("nanos", u64::from(duration.subsec_nanos()))];
+                visitor.visit_map(MapDeserializer::new(fields.into_iter()))
+            }
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            MarinValue::Int(i) => visitor.visit_f64(*i as f64),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            MarinValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// `std::ops` ranges only accept range literals of their own shape.
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, _fields: &'static [&'static str], visitor: V)
+                                           -> Result<V::Value> {
+        let range = match self.0 {
+            MarinValue::Range(range) => range,
+            _ => return self.deserialize_any(visitor),
+        };
+        let (bounds, expected) = match name {
+            "Range" => (range.as_range().map(|r| vec![("start", r.start), ("end", r.end)]), "a..b"),
+            "RangeInclusive" =>
+                (range.as_range_inclusive().map(|r| vec![("start", *r.start()), ("end", *r.end())]), "a..=b"),
+            "RangeFrom" => (range.as_range_from().map(|r| vec![("start", r.start)]), "a.."),
+            "RangeTo" => (range.as_range_to().map(|r| vec![("end", r.end)]), "..b"),
+            _ => return self.deserialize_any(visitor),
+        };
+        match bounds {
+            Some(bounds) => visitor.visit_map(MapDeserializer::new(bounds.into_iter())),
+            None => Err(de::Error::custom(format!("expected a range like `{}`, found `{}`", expected, range))),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V)
+                                         -> Result<V::Value> {
+        match self.0 {
+            MarinValue::String(s) => s.as_str().into_deserializer().deserialize_enum(name, variants, visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use serde::Deserialize;
+
+    use crate::{de, Error, Marin, MarinValue, Result};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct BanArgs {
+        reason: String,
+        chats: Vec<i64>,
+        #[serde(default)]
+        silent: bool,
+        #[serde(default)]
+        args: Vec<i64>,
+    }
+
+    #[test]
+    fn struct_from_keywords() -> Result<()> {
+        let args: BanArgs = de::from_str("777000 reason: spam chats: [-1001129887931, 2] -silent")?;
+        assert_eq!(args, BanArgs {
+            reason: "spam".into(),
+            chats: vec![-1001129887931, 2],
+            silent: true,
+            args: vec![777000],
+        });
+        let args: BanArgs = de::from_str("reason: \"spam bot\" chats: []")?;
+        assert!(!args.silent);
+        assert!(args.args.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn custom_positional_field() -> Result<()> {
+        #[derive(Deserialize)]
+        struct Lookup {
+            targets: Vec<String>,
+            #[serde(default)]
+            id: bool,
+        }
+
+        let m = Marin::parse("@username @other -id")?;
+        let lookup = Lookup::deserialize(de::Deserializer::new(&m).positional_field("targets"))?;
+        assert_eq!(lookup.targets, vec!["@username".to_string(), "@other".to_string()]);
+        assert!(lookup.id);
+        Ok(())
+    }
+
+    #[test]
+    fn positionals_as_tuple() -> Result<()> {
+        let (user, reason): (i64, String) = de::from_str("777000 \"ban reason\"")?;
+        assert_eq!((user, reason.as_str()), (777000, "ban reason"));
+        Ok(())
+    }
+
+    #[test]
+    fn ranges_options_and_enums() -> Result<()> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Mode { Ban, Kick }
+
+        #[derive(Deserialize)]
+        struct Purge {
+            ids: Range<i64>,
+            mode: Mode,
+            limit: Option<u32>,
+            ratio: f64,
+        }
+
+        let purge: Purge = de::from_str("ids: -5..5 mode: kick ratio: 1")?;
+        assert_eq!(purge.ids, -5..5);
+        assert!(matches!(purge.mode, Mode::Kick));
+        assert_eq!(purge.limit, None);
+        assert_eq!(purge.ratio, 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn null_is_none() -> Result<()> {
+        #[derive(Deserialize)]
+        struct Edit {
+            reason: Option<String>,
+            chats: Vec<Option<i64>>,
+        }
+
+        let edit: Edit = de::from_str("reason: none chats: [1, null]")?;
+        assert_eq!(edit.reason, None);
+        assert_eq!(edit.chats, vec![Some(1), None]);
+        Ok(())
+    }
+
+    #[test]
+    fn nested_maps() -> Result<()> {
+        use std::collections::HashMap;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Override {
+            mode: String,
+            #[serde(default)]
+            limit: Option<u32>,
+        }
+
+        #[derive(Deserialize)]
+        struct Config {
+            overrides: HashMap<String, Override>,
+        }
+
+        let config: Config = de::from_str("overrides: {\"-100123\": {mode: kick, limit: 5}, other: {mode: ban}}")?;
+        assert_eq!(config.overrides["-100123"], Override { mode: "kick".into(), limit: Some(5) });
+        assert_eq!(config.overrides["other"].limit, None);
+        Ok(())
+    }
+
+    #[test]
+    fn range_shapes() -> Result<()> {
+        use std::ops::{RangeFrom, RangeInclusive, RangeTo};
+
+        #[derive(Deserialize)]
+        struct Ranges {
+            inclusive: RangeInclusive<i64>,
+            from: RangeFrom<i64>,
+            to: RangeTo<i64>,
+        }
+
+        let ranges: Ranges = de::from_str("inclusive: 1..=5 from: 3.. to: ..10")?;
+        assert_eq!((ranges.inclusive, ranges.from, ranges.to), (1..=5, 3.., ..10));
+        let error = de::from_str::<Ranges>("inclusive: 1..5 from: 3.. to: ..10").err().unwrap();
+        assert_eq!(error.to_string(), "`inclusive`: expected a range like `a..=b`, found `1..5`");
+        Ok(())
+    }
+
+    #[test]
+    fn errors_name_the_keyword() {
+        match de::from_str::<BanArgs>("reason: spam chats: \"abc\"") {
+            Err(Error::Deserialize { key, message }) => {
+                assert_eq!(key.as_deref(), Some("chats"));
+                assert!(message.contains("expected a sequence"), "{}", message);
+            }
+            other => panic!("expected a deserialize error, got {:?}", other),
+        }
+        match de::from_str::<BanArgs>("chats: [1]") {
+            Err(Error::Deserialize { key, .. }) => assert_eq!(key.as_deref(), Some("reason")),
+            other => panic!("expected a deserialize error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn marin_value() -> Result<()> {
+        let value = MarinValue::List(vec![1.into(), 2.into()]);
+        let list: Vec<u8> = Deserialize::deserialize(de::ValueDeserializer(&value))?;
+        assert_eq!(list, vec![1, 2]);
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+
+use pest::error::{ErrorVariant, InputLocation, LineColLocation};
+
+use crate::parser::Rule;
+use crate::span::Span;
+
+/// A human readable description of a problem in the input.
+///
+/// The `Display` impl renders the message followed by the offending line
+/// and a caret under the failing position, e.g.
+///
+/// ```text
+/// unexpected `:`, expected end of input, value, flag or word at line 1, column 6
+/// -flag: 123
+///      ^
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+    /// 1-based line of `span.start`.
+    pub line: usize,
+    /// 1-based column of `span.start`, counted in characters.
+    pub column: usize,
+    /// Plain-language names of what the parser would have accepted.
+    pub expected: Vec<&'static str>,
+    pub severity: Severity,
+    line_text: String,
+    /// Length of the underline in characters.
+    width: usize,
+}
+
+impl Diagnostic {
+    /// Create a diagnostic for `span` in `input`.
+    ///
+    /// A span outside of `input` or inside a character is moved back to the closest position in it.
+    pub fn new(message: impl Into<String>, span: Span, input: &str) -> Self {
+        let start = char_boundary(input, span.start);
+        let span = Span::new(start, char_boundary(input, span.end).max(start));
+        let before = &input[..span.start];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[span.start..].find('\n').map_or(input.len(), |i| span.start + i);
+        let line_text = input[line_start..line_end].trim_end_matches('\r').to_string();
+        let width = input[span.start..span.end.min(line_end).max(span.start)].chars().count();
+
+        Diagnostic {
+            message: message.into(),
+            span,
+            line: before.matches('\n').count() + 1,
+            column: input[line_start..span.start].chars().count() + 1,
+            expected: vec![],
+            severity: Severity::Error,
+            line_text,
+            width: width.max(1),
+        }
+    }
+
+    /// Move a diagnostic for a command into the line of a script, the command starts at `column` of
+    /// `line` and at byte `offset` of the script.
+    pub(crate) fn relocate(mut self, line: usize, column: usize, offset: usize, line_text: &str) -> Self {
+        self.line = line;
+        self.column += column - 1;
+        self.span = Span::new(self.span.start + offset, self.span.end + offset);
+        self.line_text = line_text.into();
+        self
+    }
+
+    /// Show the line of `input` instead of the one the parser saw, for input that was parsed with
+    /// parts blanked out.
+    pub(crate) fn show_line_of(mut self, input: &str) -> Self {
+        if input.get(self.span.start..self.span.end).is_some() {
+            self.line_text = Diagnostic::new("", self.span, input).line_text;
+        }
+        self
+    }
+}
+
+/// `index` moved back onto a char boundary of `input`.
+fn char_boundary(input: &str, index: usize) -> usize {
+    let mut index = index.min(input.len());
+    while !input.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// How bad a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The input was accepted but probably does not mean what was intended.
+    Warning,
+    /// Part of the input could not be parsed.
+    Error,
+}
+
+impl From<&pest::error::Error<Rule>> for Diagnostic {
+    fn from(error: &pest::error::Error<Rule>) -> Self {
+        let (line, column) = match error.line_col {
+            LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+        };
+        let span = match error.location {
+            InputLocation::Pos(pos) => Span::new(pos, pos),
+            InputLocation::Span((start, end)) => Span::new(start, end),
+        };
+        let line_text = error.line().trim_end_matches(&['\r', '\n'][..]).to_string();
+        let rest: String = line_text.chars().skip(column - 1).collect();
+        let width = match error.line_col {
+            LineColLocation::Span((start_line, start_col), (end_line, end_col)) if start_line == end_line =>
+                end_col.saturating_sub(start_col),
+            _ => 1,
+        };
+
+        let (message, expected) = match &error.variant {
+            ErrorVariant::CustomError { message } => (message.clone(), vec![]),
+            ErrorVariant::ParsingError { positives, .. } => {
+                let before: String = line_text.chars().take(column - 1).collect();
+                if let Some(quote) = unterminated_quote(&before, &rest) {
+                    let expected = vec!["closing quote"];
+                    (format!("unterminated string, expected closing quote `{}`", quote), expected)
+                } else if let (Some(_), Some(bracket)) = (unclosed_bracket(&before), unclosed_bracket(&line_text)) {
+                    match bracket {
+                        '{' => ("unterminated map, expected closing brace `}`".to_string(), vec!["closing brace"]),
+                        _ => ("unterminated list, expected closing bracket `]`".to_string(), vec!["closing bracket"]),
+                    }
+                } else {
+                    let mut expected: Vec<&'static str> = vec![];
+                    for rule in positives {
+                        let description = rule.description();
+                        if !description.is_empty() && !expected.contains(&description) {
+                            expected.push(description);
+                        }
+                    }
+                    let found = match rest.chars().next() {
+                        Some(c) if !c.is_whitespace() => format!("unexpected `{}`", c),
+                        _ => "unexpected end of input".to_string(),
+                    };
+                    if expected.is_empty() {
+                        (found, expected)
+                    } else {
+                        (format!("{}, expected {}", found, join_expected(&expected)), expected)
+                    }
+                }
+            }
+        };
+
+        Diagnostic { message, span, line, column, expected, severity: Severity::Error, line_text, width: width.max(1) }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.severity == Severity::Warning {
+            write!(f, "warning: ")?;
+        }
+        writeln!(f, "{} at line {}, column {}", self.message, self.line, self.column)?;
+        writeln!(f, "{}", self.line_text)?;
+        write!(f, "{}{}", " ".repeat(self.column - 1), "^".repeat(self.width))
+    }
+}
+
+/// Join descriptions as `a, b or c`.
+fn join_expected(expected: &[&str]) -> String {
+    match expected.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+/// The quote character that opens a string before or at the error position and is never closed.
+fn unterminated_quote(before: &str, rest: &str) -> Option<char> {
+    let mut open = None;
+    let mut escaped = false;
+    for (i, c) in before.chars().chain(rest.chars()).enumerate() {
+        match (open, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some((_, q)), c) if c == q => open = None,
+            (None, '"') | (None, '\'') => open = Some((i, c)),
+            _ => (),
+        }
+    }
+    open.filter(|(i, _)| *i <= before.chars().count()).map(|(_, quote)| quote)
+}
+
+/// The innermost `[` or `{` of `line` that is never closed.
+fn unclosed_bracket(line: &str) -> Option<char> {
+    let mut open = vec![];
+    for c in line.chars() {
+        match c {
+            '[' | '{' => open.push(c),
+            ']' | '}' => {
+                open.pop();
+            }
+            _ => (),
+        }
+    }
+    open.pop()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Diagnostic, Error, Marin, Span};
+
+    fn diagnostic(input: &str) -> Diagnostic {
+        match Marin::parse(input) {
+            Err(Error::Parser(e)) => Diagnostic::from(&e),
+            other => panic!("expected a parser error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn flag_with_value() {
+        let d = diagnostic("-flag: 123");
+        assert_eq!((d.line, d.column), (1, 6));
+        assert_eq!(d.to_string(), "unexpected `:`, expected end of input, value, flag or word at line 1, column 6\n\
+                                   -flag: 123\n     ^");
+    }
+
+    #[test]
+    fn missing_flag_name() {
+        let d = diagnostic("1 -");
+        assert_eq!(d.expected, vec!["flag name"]);
+        assert_eq!(d.message, "unexpected end of input, expected flag name");
+    }
+
+    #[test]
+    fn unterminated_string() {
+        let d = diagnostic("reason: \"spam");
+        assert_eq!(d.expected, vec!["closing quote"]);
+        assert_eq!(d.to_string(), "unterminated string, expected closing quote `\"` at line 1, column 9\n\
+                                   reason: \"spam\n        ^");
+    }
+
+    #[test]
+    fn unterminated_single_quote() {
+        let d = diagnostic("reason: 'it\\'s spam");
+        assert_eq!(d.message, "unterminated string, expected closing quote `'`");
+    }
+
+    #[test]
+    fn unterminated_later() {
+        assert!(diagnostic("-flag: 1 reason: \"spam").message.starts_with("unexpected `:`"));
+        assert!(diagnostic("-flag: 1 ids: [1, 2").message.starts_with("unexpected `:`"));
+    }
+
+    #[test]
+    fn unterminated_map() {
+        let d = diagnostic("overrides: {a: 1, b: [2]");
+        assert_eq!(d.message, "unterminated map, expected closing brace `}`");
+        assert_eq!(d.expected, vec!["closing brace"]);
+    }
+
+    #[test]
+    fn unterminated_list() {
+        let d = diagnostic("chats: [1, 2");
+        assert_eq!(d.expected, vec!["closing bracket"]);
+    }
+
+    #[test]
+    fn second_line() {
+        let d = diagnostic("1\n-");
+        assert_eq!((d.line, d.column), (2, 2));
+        assert!(d.to_string().ends_with("\n-\n ^"));
+    }
+
+    #[test]
+    fn from_span() {
+        let d = Diagnostic::new("bad value", Span::new(8, 11), "1 2\nkw: abc");
+        assert_eq!((d.line, d.column), (2, 5));
+        assert_eq!(d.to_string(), "bad value at line 2, column 5\nkw: abc\n    ^^^");
+    }
+
+    #[test]
+    fn mismatched_input() {
+        let error = Error::DuplicateKeyword { key: "reason".into(), span: Span::new(8, 20) };
+        assert!(error.to_diagnostic("short").is_none());
+        assert!(error.to_diagnostic("reason: a reason: b").is_none());
+        assert!(error.to_diagnostic("reason: a reason: b c").is_some());
+
+        let d = Diagnostic::new("bad value", Span::new(8, 20), "short");
+        assert_eq!((d.span, d.column), (Span::new(5, 5), 6));
+        let d = Diagnostic::new("bad value", Span::new(1, 2), "ü");
+        assert_eq!((d.span, d.column), (Span::new(0, 2), 1));
+    }
+}
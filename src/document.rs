@@ -0,0 +1,165 @@
+use crate::diagnostic::Diagnostic;
+use crate::error::Result;
+use crate::options::ParseOptions;
+use crate::span::Span;
+use crate::{Marin, OwnedMarin};
+
+/// One command of a script parsed with [`Marin::parse_documents`].
+#[derive(Debug)]
+pub struct Document {
+    /// 1-based line of the command.
+    pub line: usize,
+    /// 1-based column the command starts at, counted in characters.
+    pub column: usize,
+    /// Where the command is in the whole script.
+    pub span: Span,
+    pub source: String,
+    pub result: Result<OwnedMarin>,
+    line_text: String,
+}
+
+impl Document {
+    /// A diagnostic pointing into the line of the script, `None` if the command parsed.
+    pub fn diagnostic(&self) -> Option<Diagnostic> {
+        let error = self.result.as_ref().err()?;
+        let diagnostic = error.to_diagnostic(&self.source)?;
+        Some(diagnostic.relocate(self.line, self.column, self.span.start, &self.line_text))
+    }
+}
+
+pub(crate) fn parse_documents(input: &str, options: &ParseOptions) -> Vec<Document> {
+    let mut documents = vec![];
+    let mut line_start = 0;
+    for (index, line) in input.split('\n').enumerate() {
+        let line_text = line.trim_end_matches('\r');
+        for (start, end) in commands(line_text, !options.punctuation.contains(';')) {
+            let source = &line_text[start..end];
+            if source.trim().is_empty() || is_comment(source.trim_start()) {
+                continue;
+            }
+            documents.push(Document {
+                line: index + 1,
+                column: line_text[..start].chars().count() + 1,
+                span: Span::new(line_start + start, line_start + end),
+                source: source.into(),
+                result: Marin::parse_with(source, options).map(Marin::into_owned),
+                line_text: line_text.into(),
+            });
+        }
+        line_start += line.len() + 1;
+    }
+    documents
+}
+
+/// Byte ranges of the `;`-separated commands in `line`, a `;` in a string, list, map or comment does not count.
+///
+/// Without `split` the whole line is one command.
+fn commands(line: &str, split: bool) -> Vec<(usize, usize)> {
+    let mut commands = vec![];
+    let mut start = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut depth = 0usize;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote.is_some() => escaped = true,
+            c if quote == Some(c) => quote = None,
+            _ if quote.is_some() => (),
+            '"' | '\'' => quote = Some(c),
+            '#' if (i == 0 || line[..i].ends_with(char::is_whitespace)) && is_comment(&line[i..]) => break,
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth = depth.saturating_sub(1),
+            ';' if split && depth == 0 => {
+                commands.push((start, i));
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    commands.push((start, line.len()));
+    commands
+}
+
+/// Whether `s` starts with a comment, `#` followed by whitespace or nothing.
+fn is_comment(s: &str) -> bool {
+    s.strip_prefix('#').is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crate::{Error, Marin, MarinValue, ParseOptions};
+
+    const SCRIPT: &str = "# ban the usual suspects\n\
+                          777000 reason: spam; 42 reason: \"a; b\"\r\n\
+                          \n\
+                          1 reason: # trailing comment; not a command\n\
+                          -silent -no-silent\n\
+                          ids: [1, 2";
+
+    #[test]
+    fn commands_and_lines() {
+        let documents = Marin::parse_documents(SCRIPT);
+        let places: Vec<_> = documents.iter().map(|d| (d.line, d.column, d.source.as_str())).collect();
+        assert_eq!(places, vec![
+            (2, 1, "777000 reason: spam"),
+            (2, 21, " 42 reason: \"a; b\""),
+            (4, 1, "1 reason: # trailing comment; not a command"),
+            (5, 1, "-silent -no-silent"),
+            (6, 1, "ids: [1, 2"),
+        ]);
+        assert_eq!(documents[1].span.as_str(SCRIPT), " 42 reason: \"a; b\"");
+        assert_eq!(documents[1].result.as_ref().unwrap().kwargs["reason"], MarinValue::from("a; b"));
+        assert!(matches!(documents[2].result, Err(Error::MissingValue { .. })));
+        assert!(matches!(documents[3].result, Err(Error::ConflictingFlags { .. })));
+        assert!(documents[0].diagnostic().is_none());
+    }
+
+    #[test]
+    fn semicolons_in_lists_and_maps() {
+        let documents = Marin::parse_documents("ids: [1; 2] opts: {a: 1; b: 2}; 3");
+        let sources: Vec<_> = documents.iter().map(|d| d.source.as_str()).collect();
+        assert_eq!(sources, vec!["ids: [1; 2] opts: {a: 1; b: 2}", " 3"]);
+    }
+
+    #[test]
+    fn semicolons_as_punctuation() {
+        let options = ParseOptions { punctuation: ";".into(), ..ParseOptions::default() };
+        let documents = Marin::parse_documents_with("a;b c", &options);
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].result.as_ref().unwrap().args, vec![MarinValue::from("a;b"), MarinValue::from("c")]);
+        assert_eq!(Marin::parse_documents("a;b c").len(), 2);
+    }
+
+    #[test]
+    fn diagnostics_point_into_the_script() {
+        let documents = Marin::parse_documents("1; 2 -silent !silent");
+        let diagnostic = documents[1].diagnostic().unwrap();
+        assert_eq!((diagnostic.line, diagnostic.column), (1, 14));
+        assert_eq!(diagnostic.span.as_str("1; 2 -silent !silent"), "!silent");
+        assert!(diagnostic.to_string().ends_with("1; 2 -silent !silent\n             ^^^^^^^"));
+
+        let documents = Marin::parse_documents("1\n  ids: [1, 2");
+        let diagnostic = documents[1].diagnostic().unwrap();
+        assert_eq!(diagnostic.line, 2);
+        assert_eq!(diagnostic.message, "unterminated list, expected closing bracket `]`");
+    }
+
+    #[test]
+    fn readers_and_files() {
+        let m = Marin::parse_reader("777000 reason: spam".as_bytes()).unwrap();
+        assert_eq!(m.kwargs["reason"], MarinValue::from("spam"));
+        assert!(matches!(Marin::parse_reader(&[0xff, 0xfe][..]), Err(Error::IO(_))));
+
+        let path = std::env::temp_dir().join(format!("marin-{}.txt", std::process::id()));
+        std::fs::File::create(&path).unwrap().write_all(b"1 -silent\n2\n3\n").unwrap();
+        let m = Marin::parse_file(&path);
+        let documents = Marin::parse_documents_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(m.unwrap().args, vec![MarinValue::Int(1), MarinValue::Int(2), MarinValue::Int(3)]);
+        assert_eq!(documents.unwrap().len(), 3);
+        assert!(matches!(Marin::parse_file(&path), Err(Error::IO(_))));
+    }
+}
@@ -2,26 +2,153 @@ use std::{error, fmt, io};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+use pest::error::InputLocation;
+
+use crate::args::{ArgError, ConversionError};
+use crate::diagnostic::Diagnostic;
 use crate::parser::Rule;
+use crate::span::Span;
 
 #[derive(Debug)]
 pub enum Error {
     IO(io::Error),
     Parser(pest::error::Error<Rule>),
+    /// An integer literal that does not fit into an `i64`.
+    IntegerOverflow { literal: String, span: Span },
+    /// A float literal that does not describe a finite `f64`.
+    InvalidFloat { literal: String, span: Span },
+    /// A range whose bound does not fit into an `i64`, `literal` and `span` refer to the bound.
+    MalformedRange { literal: String, span: Span },
+    /// An unknown escape sequence or invalid `\u{...}` escape in a quoted string.
+    InvalidEscape { escape: String, span: Span },
+    /// A word with punctuation that is not in [`ParseOptions::punctuation`](crate::ParseOptions::punctuation).
+    DisallowedPunctuation { character: char, span: Span },
+    /// A keyword like `reason:` without a value, `span` covers the key and colon.
+    MissingValue { key: String, span: Span },
+    /// A keyword given more than once with [`DuplicateKeywords::Error`](crate::DuplicateKeywords::Error),
+    /// `span` is the repeated argument.
+    DuplicateKeyword { key: String, span: Span },
+    /// A flag that is both set and negated, `span` is the later one.
+    ConflictingFlags { flag: String, span: Span },
+    /// A range step that is not positive.
+    InvalidStep { literal: String, span: Span },
+    /// A duration literal longer than `u64::MAX` milliseconds.
+    DurationOverflow { literal: String, span: Span },
+    /// A list or map nested deeper than [`MAX_NESTING`](crate::MAX_NESTING), `span` is its opening bracket.
+    NestingTooDeep { span: Span },
+    /// A value could not be deserialized, `key` names the keyword argument it came from.
+    Deserialize { key: Option<String>, message: String },
+    /// A value has no representation in Marin syntax.
+    Serialize(String),
+    /// The arguments did not match what a [`MarinArgs`](crate::MarinArgs) type accepts.
+    Arguments(Vec<ArgError>),
+    /// A value taken out of [`Marin`](crate::Marin) did not have the requested type.
+    Conversion(ConversionError),
+    /// A line that does not start with a registered command, `span` is the unknown word.
+    UnknownCommand { command: String, span: Span },
+    /// A command that can only be run through one of its `subcommands`, `span` covers the command words.
+    IncompleteCommand { command: String, subcommands: Vec<String>, span: Span },
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            _ => write!(f, "{:?}", self)
+            Error::IO(e) => write!(f, "{}", e),
+            Error::Parser(e) => write!(f, "{}", Diagnostic::from(e)),
+            Error::IntegerOverflow { literal, .. } =>
+                write!(f, "integer `{}` does not fit into 64 bits", literal),
+            Error::InvalidFloat { literal, .. } =>
+                write!(f, "`{}` is not a finite number", literal),
+            Error::MalformedRange { literal, .. } =>
+                write!(f, "range bound `{}` does not fit into 64 bits", literal),
+            Error::InvalidEscape { escape, .. } => write!(f, "invalid escape sequence `{}`", escape),
+            Error::DisallowedPunctuation { character, .. } =>
+                write!(f, "`{}` is not allowed in unquoted words", character),
+            Error::MissingValue { key, .. } => write!(f, "keyword `{}` has no value", key),
+            Error::DuplicateKeyword { key, .. } => write!(f, "keyword `{}` is given more than once", key),
+            Error::ConflictingFlags { flag, .. } => write!(f, "flag `{}` is both set and negated", flag),
+            Error::InvalidStep { literal, .. } =>
+                write!(f, "range step `{}` has to be positive", literal),
+            Error::DurationOverflow { literal, .. } =>
+                write!(f, "duration `{}` is too long", literal),
+            Error::NestingTooDeep { .. } =>
+                write!(f, "lists and maps may not be nested more than {} levels deep", crate::MAX_NESTING),
+            Error::Deserialize { key: Some(key), message } => write!(f, "`{}`: {}", key, message),
+            Error::Deserialize { key: None, message } => write!(f, "{}", message),
+            Error::Serialize(message) => write!(f, "{}", message),
+            Error::Arguments(errors) => {
+                let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "{}", errors.join("\n"))
+            }
+            Error::Conversion(e) => write!(f, "{}", e),
+            Error::UnknownCommand { command, .. } if command.is_empty() => write!(f, "no command given"),
+            Error::UnknownCommand { command, .. } => write!(f, "unknown command `{}`", command),
+            Error::IncompleteCommand { command, subcommands, .. } if subcommands.is_empty() =>
+                write!(f, "`{}` can not be run on its own", command),
+            Error::IncompleteCommand { command, subcommands, .. } =>
+                write!(f, "`{}` needs a subcommand, one of {}", command, subcommands.join(", ")),
         }
+    }
+}
 
+impl Error {
+    /// The span of the input this error refers to, if any.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::IO(_) | Error::Deserialize { .. } | Error::Serialize(_) | Error::Conversion(_) => None,
+            Error::Arguments(errors) => errors.iter().find_map(|e| e.span),
+            Error::Parser(e) => Some(match e.location {
+                InputLocation::Pos(pos) => Span::new(pos, pos),
+                InputLocation::Span((start, end)) => Span::new(start, end),
+            }),
+            Error::IntegerOverflow { span, .. }
+            | Error::InvalidFloat { span, .. }
+            | Error::MalformedRange { span, .. }
+            | Error::InvalidEscape { span, .. }
+            | Error::DisallowedPunctuation { span, .. }
+            | Error::MissingValue { span, .. }
+            | Error::DuplicateKeyword { span, .. }
+            | Error::ConflictingFlags { span, .. }
+            | Error::InvalidStep { span, .. }
+            | Error::DurationOverflow { span, .. }
+            | Error::NestingTooDeep { span }
+            | Error::UnknownCommand { span, .. }
+            | Error::IncompleteCommand { span, .. } => Some(*span),
+        }
+    }
+
+    /// A [`Diagnostic`] pointing into `input`, the string that failed to parse.
+    ///
+    /// `None` for errors without a span, or with a span that does not fit into `input`. Argument errors give the
+    /// diagnostic of the first one, see [`ArgError::to_diagnostic`](crate::ArgError::to_diagnostic) for the others.
+    pub fn to_diagnostic(&self, input: &str) -> Option<Diagnostic> {
+        match self {
+            Error::IO(_) | Error::Deserialize { .. } | Error::Serialize(_) | Error::Conversion(_) => None,
+            Error::Parser(e) => Some(Diagnostic::from(e).show_line_of(input)),
+            Error::Arguments(errors) => {
+                let error = errors.first()?;
+                if let Some(span) = error.span {
+                    input.get(span.start..span.end)?;
+                }
+                Some(error.to_diagnostic(input))
+            }
+            _ => {
+                let span = self.span()?;
+                input.get(span.start..span.end)?;
+                Some(Diagnostic::new(self.to_string(), span, input))
+            }
+        }
     }
 }
 
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        None
+        match self {
+            Error::IO(e) => Some(e),
+            Error::Parser(e) => Some(e),
+            Error::Conversion(e) => Some(e),
+            _ => None,
+        }
     }
 }
 
@@ -31,8 +158,32 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<ConversionError> for Error {
+    fn from(item: ConversionError) -> Self {
+        Error::Conversion(item)
+    }
+}
+
 impl From<pest::error::Error<Rule>> for Error {
     fn from(item: pest::error::Error<Rule>) -> Self {
         Error::Parser(item)
     }
 }
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Deserialize { key: None, message: msg.to_string() }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error::Deserialize { key: Some(field.into()), message: "missing keyword argument".into() }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Serialize(msg.to_string())
+    }
+}
@@ -0,0 +1,359 @@
+use crate::args::ArgKind;
+use crate::command::{Command, CommandRegistry};
+use crate::schema::{Arg, Schema};
+
+/// The markup of help text made by [`CommandRegistry::help`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelpFormat {
+    /// Aligned columns for terminals.
+    Plain,
+    /// Telegram's MarkdownV2.
+    Markdown,
+    /// The HTML subset Telegram accepts.
+    Html,
+}
+
+impl Schema {
+    /// The arguments in the order they are written, like `<user> reason: <string> [-silent]`.
+    ///
+    /// Optional arguments are in brackets.
+    pub fn usage(&self) -> String {
+        let mut parts: Vec<String> = self.positionals().map(usage).collect();
+        parts.extend(self.arguments().iter().filter(|arg| arg.kind != ArgKind::Positional).map(usage));
+        parts.join(" ")
+    }
+}
+
+fn usage(arg: &Arg) -> String {
+    let usage = match arg.kind {
+        ArgKind::Positional => format!("<{}>", arg.name),
+        ArgKind::Keyword => format!("{}: <{}>", arg.name, arg.value_type),
+        ArgKind::Flag => format!("-{}", arg.name),
+    };
+    if arg.required {
+        usage
+    } else {
+        format!("[{}]", usage)
+    }
+}
+
+/// Type, constraints and default of `arg`, like `int, between 1 and 100, default: 10`.
+fn details(arg: &Arg) -> String {
+    let mut details = vec![match arg.kind {
+        ArgKind::Flag => "flag".to_string(),
+        _ => arg.value_type.to_string(),
+    }];
+    if !arg.allowed.is_empty() {
+        let allowed: Vec<_> = arg.allowed.iter().map(ToString::to_string).collect();
+        details.push(format!("one of {}", allowed.join(", ")));
+    }
+    match (arg.min, arg.max) {
+        (Some(min), Some(max)) => details.push(format!("between {} and {}", min, max)),
+        (Some(min), None) => details.push(format!("at least {}", min)),
+        (None, Some(max)) => details.push(format!("at most {}", max)),
+        (None, None) => (),
+    }
+    if arg.required {
+        details.push("required".into());
+    }
+    if let Some(default) = &arg.default {
+        details.push(format!("default: {}", default));
+    }
+    details.join(", ")
+}
+
+struct Row {
+    term: String,
+    details: String,
+    help: String,
+}
+
+/// Help text before it is rendered in one of the formats.
+enum Block {
+    Title { name: String, help: String },
+    Usage(String),
+    Section { title: &'static str, rows: Vec<Row> },
+    Examples(Vec<String>),
+}
+
+impl<C, R> CommandRegistry<C, R> {
+    /// An overview with the usage and help of every command that can be run, subcommands included.
+    pub fn help(&self, format: HelpFormat) -> String {
+        let mut rows = vec![];
+        for command in self.commands() {
+            runnable_rows(command, &mut vec![], &mut rows);
+        }
+        render(&[Block::Section { title: "Commands", rows }], format)
+    }
+
+    /// Usage, arguments, subcommands and examples of the command called `name`, like `gban add`.
+    ///
+    /// `None` if there is no such command.
+    pub fn command_help(&self, name: &str, format: HelpFormat) -> Option<String> {
+        let mut words = name.split_whitespace();
+        let mut command = self.command_named(words.next()?)?;
+        let mut path = vec![command.name.as_str()];
+        for word in words {
+            command = command.subcommand_named(word)?;
+            path.push(command.name.as_str());
+        }
+        Some(render(&command_blocks(command, &path.join(" ")), format))
+    }
+}
+
+fn runnable_rows<'c, C, R>(command: &'c Command<C, R>, path: &mut Vec<&'c str>, rows: &mut Vec<Row>) {
+    path.push(&command.name);
+    if command.has_handler() || command.subcommands.is_empty() {
+        rows.push(Row { term: command_usage(command, path), details: String::new(), help: command.help.clone() });
+    }
+    for subcommand in &command.subcommands {
+        runnable_rows(subcommand, path, rows);
+    }
+    path.pop();
+}
+
+fn command_usage<C, R>(command: &Command<C, R>, path: &[&str]) -> String {
+    let usage = command.schema.usage();
+    match (path.join(" "), usage.is_empty()) {
+        (path, true) => path,
+        (path, false) => format!("{} {}", path, usage),
+    }
+}
+
+fn command_blocks<C, R>(command: &Command<C, R>, path: &str) -> Vec<Block> {
+    let mut name = path.to_string();
+    if !command.aliases.is_empty() {
+        name = format!("{} ({})", name, command.aliases.join(", "));
+    }
+    let mut blocks = vec![Block::Title { name, help: command.help.clone() }];
+    if command.has_handler() || command.subcommands.is_empty() {
+        let path: Vec<_> = path.split(' ').collect();
+        blocks.push(Block::Usage(command_usage(command, &path)));
+    }
+    let arguments: Vec<_> = command.schema.arguments().iter().map(|arg| {
+        let term = match arg.kind {
+            ArgKind::Positional => format!("<{}>", arg.name),
+            ArgKind::Keyword => arg.name.clone(),
+            ArgKind::Flag => format!("-{}", arg.name),
+        };
+        let mut names = vec![term];
+        names.extend(arg.aliases.iter().map(|alias| match arg.kind {
+            ArgKind::Flag => format!("-{}", alias),
+            _ => alias.clone(),
+        }));
+        Row { term: names.join(", "), details: details(arg), help: arg.help.clone() }
+    }).collect();
+    if !arguments.is_empty() {
+        blocks.push(Block::Section { title: "Arguments", rows: arguments });
+    }
+    let subcommands: Vec<_> = command.subcommands.iter().map(|subcommand| {
+        let mut names = vec![subcommand.name.clone()];
+        names.extend(subcommand.aliases.iter().cloned());
+        Row { term: names.join(", "), details: String::new(), help: subcommand.help.clone() }
+    }).collect();
+    if !subcommands.is_empty() {
+        blocks.push(Block::Section { title: "Subcommands", rows: subcommands });
+    }
+    if !command.examples.is_empty() {
+        blocks.push(Block::Examples(command.examples.clone()));
+    }
+    blocks
+}
+
+fn render(blocks: &[Block], format: HelpFormat) -> String {
+    let rendered: Vec<_> = blocks.iter().map(|block| match format {
+        HelpFormat::Plain => plain(block),
+        HelpFormat::Markdown => markup(block, &MARKDOWN),
+        HelpFormat::Html => markup(block, &HTML),
+    }).collect();
+    rendered.join("\n\n")
+}
+
+fn plain(block: &Block) -> String {
+    match block {
+        Block::Title { name, help } if help.is_empty() => name.clone(),
+        Block::Title { name, help } => format!("{} - {}", name, help),
+        Block::Usage(usage) => format!("Usage: {}", usage),
+        Block::Section { title, rows } => {
+            let width =
+                |column: fn(&Row) -> &str| rows.iter().map(|row| column(row).chars().count()).max().unwrap_or(0);
+            let (term_width, details_width) = (width(|row| &row.term), width(|row| &row.details));
+            let mut lines = vec![format!("{}:", title)];
+            for row in rows {
+                let line = match details_width {
+                    0 => format!("  {:<2$}  {}", row.term, row.help, term_width),
+                    _ => format!("  {:<3$}  {:<4$}  {}", row.term, row.details, row.help, term_width, details_width),
+                };
+                lines.push(line.trim_end().to_string());
+            }
+            lines.join("\n")
+        }
+        Block::Examples(examples) => {
+            let mut lines = vec!["Examples:".to_string()];
+            lines.extend(examples.iter().map(|example| format!("  {}", example)));
+            lines.join("\n")
+        }
+    }
+}
+
+/// How [`HelpFormat::Markdown`] and [`HelpFormat::Html`] mark up text.
+struct Markup {
+    bold: (&'static str, &'static str),
+    code: (&'static str, &'static str),
+    text: fn(&str) -> String,
+    code_text: fn(&str) -> String,
+    dash: &'static str,
+}
+
+const MARKDOWN: Markup = Markup {
+    bold: ("*", "*"),
+    code: ("`", "`"),
+    text: escape_markdown,
+    code_text: escape_markdown_code,
+    dash: " \\- ",
+};
+
+const HTML: Markup = Markup {
+    bold: ("<b>", "</b>"),
+    code: ("<code>", "</code>"),
+    text: escape_html,
+    code_text: escape_html,
+    dash: " - ",
+};
+
+impl Markup {
+    fn bold(&self, s: &str) -> String {
+        format!("{}{}{}", self.bold.0, (self.text)(s), self.bold.1)
+    }
+
+    fn code(&self, s: &str) -> String {
+        format!("{}{}{}", self.code.0, (self.code_text)(s), self.code.1)
+    }
+}
+
+fn markup(block: &Block, markup: &Markup) -> String {
+    match block {
+        Block::Title { name, help } if help.is_empty() => markup.bold(name),
+        Block::Title { name, help } => format!("{}{}{}", markup.bold(name), markup.dash, (markup.text)(help)),
+        Block::Usage(usage) => markup.code(usage),
+        Block::Section { title, rows } => {
+            let mut lines = vec![markup.bold(title)];
+            for row in rows {
+                let mut line = markup.code(&row.term);
+                if !row.details.is_empty() {
+                    line = format!("{} {}", line, (markup.text)(&format!("({})", row.details)));
+                }
+                if !row.help.is_empty() {
+                    line = format!("{}{}{}", line, markup.dash, (markup.text)(&row.help));
+                }
+                lines.push(line);
+            }
+            lines.join("\n")
+        }
+        Block::Examples(examples) => {
+            let mut lines = vec![markup.bold("Examples")];
+            lines.extend(examples.iter().map(|example| markup.code(example)));
+            lines.join("\n")
+        }
+    }
+}
+
+fn escape_markdown(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "_*[]()~`>#+-=|{}.!\\".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_markdown_code(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arg, Command, CommandRegistry, HelpFormat, ValueType};
+
+    fn registry() -> CommandRegistry<()> {
+        CommandRegistry::new()
+            .command(Command::new("gban")
+                .help("Manage the global ban list.")
+                .subcommand(Command::new("add")
+                    .help("Ban a user in all chats.")
+                    .arg(Arg::positional("user", ValueType::Int).required().help("The user to ban."))
+                    .arg(Arg::keyword("reason", ValueType::String).alias("r").required())
+                    .arg(Arg::keyword("limit", ValueType::Int).min(1.0).max(100.0).default(10))
+                    .arg(Arg::keyword("mode", ValueType::String).allowed(vec!["ban", "kick"]))
+                    .arg(Arg::flag("silent").help("Do not send a message."))
+                    .example("gban add 777000 r: spam -silent")
+                    .handler(|_, _| ()))
+                .subcommand(Command::new("remove").alias("rm").help("Unban a user.").handler(|_, _| ())))
+            .command(Command::new("ping").handler(|_, _| ()))
+    }
+
+    #[test]
+    fn usage() {
+        let registry = registry();
+        let add = &registry.commands()[0].subcommands[0];
+        assert_eq!(add.schema.usage(), "<user> reason: <string> [limit: <int>] [mode: <string>] [-silent]");
+        assert_eq!(registry.help(HelpFormat::Plain), "\
+Commands:
+  gban add <user> reason: <string> [limit: <int>] [mode: <string>] [-silent]  Ban a user in all chats.
+  gban remove                                                                 Unban a user.
+  ping");
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(registry().command_help("gban add", HelpFormat::Plain).unwrap(), "\
+gban add - Ban a user in all chats.
+
+Usage: gban add <user> reason: <string> [limit: <int>] [mode: <string>] [-silent]
+
+Arguments:
+  <user>     int, required                        The user to ban.
+  reason, r  string, required
+  limit      int, between 1 and 100, default: 10
+  mode       string, one of ban, kick
+  -silent    flag                                 Do not send a message.
+
+Examples:
+  gban add 777000 r: spam -silent");
+        assert_eq!(registry().command_help("gban", HelpFormat::Plain).unwrap(), "\
+gban - Manage the global ban list.
+
+Subcommands:
+  add         Ban a user in all chats.
+  remove, rm  Unban a user.");
+        assert!(registry().command_help("gban list", HelpFormat::Plain).is_none());
+    }
+
+    #[test]
+    fn markdown() {
+        let help = registry().command_help("gban add", HelpFormat::Markdown).unwrap();
+        assert!(help.starts_with("*gban add* \\- Ban a user in all chats\\.\n\n`gban add <user> reason: <string>"));
+        assert!(help.contains("\n`limit` \\(int, between 1 and 100, default: 10\\)\n"));
+        assert!(help.contains("\n`-silent` \\(flag\\) \\- Do not send a message\\.\n"));
+        assert!(help.ends_with("*Examples*\n`gban add 777000 r: spam -silent`"));
+    }
+
+    #[test]
+    fn html() {
+        let help = registry().command_help("gban add", HelpFormat::Html).unwrap();
+        assert!(help.starts_with("<b>gban add</b> - Ban a user in all chats.\n\n<code>gban add &lt;user&gt; reason:"));
+        assert!(help.contains("\n<code>&lt;user&gt;</code> (int, required) - The user to ban.\n"));
+        assert_eq!(registry().command_help("gban", HelpFormat::Html).unwrap(), "\
+<b>gban</b> - Manage the global ban list.
+
+<b>Subcommands</b>
+<code>add</code> - Ban a user in all chats.
+<code>remove, rm</code> - Unban a user.");
+    }
+}
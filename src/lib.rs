@@ -1,129 +1,536 @@
 #[macro_use]
-extern crate pest;
-#[macro_use]
 extern crate pest_derive;
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt;
-use std::fmt::{Display, Formatter};
-use std::ops::Range;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+use indexmap::IndexMap;
 
-use pest::iterators::Pair;
+use pest::error::ErrorVariant;
+use pest::iterators::{Pair, Pairs};
 use pest::Parser;
 
+pub use args::{ArgError, ArgErrorKind, ArgKind, ArgReader, ConversionError, FromMarinValue, MarinArgs};
+pub use command::{Command, CommandRegistry, Invocation};
+pub use complete::{Completion, CompletionKind};
+pub use diagnostic::{Diagnostic, Severity};
+pub use document::Document;
 pub use error::Error;
+pub use help::HelpFormat;
 use error::Result;
-use marin_value::MarinValue;
+use options::disallowed_punctuation;
+#[cfg(feature = "derive")]
+pub use marin_derive::MarinArgs;
+pub use marin_value::{MarinValue, ValueKind, ValueType};
+pub use options::{DanglingKeyword, DuplicateKeywords, ParseOptions, DEFAULT_PUNCTUATION, WORD_PUNCTUATION};
+pub use range::{MarinRange, RangeIter};
 use parser::*;
+pub use recover::Recovered;
+pub use schema::{Arg, Schema};
+pub use span::{Span, Spanned, SpannedArgument, SpannedKind, SpannedMarin, SpannedValue};
+pub use warning::{Warning, WarningCode};
+
+mod args;
+
+mod command;
+
+mod complete;
+
+#[cfg(feature = "serde")]
+pub mod de;
+
+mod diagnostic;
+
+mod document;
 
 mod error;
 
+mod help;
+
 mod parser;
+mod printer;
+
+#[cfg(feature = "serde")]
+pub mod ser;
 
 mod marin_value;
 
-#[derive(Debug, PartialEq)]
+mod options;
+
+mod range;
+
+mod recover;
+
+mod schema;
+
+mod span;
+
+mod warning;
+
+#[cfg(feature = "telegram")]
+pub mod telegram;
+
+/// How deep lists and maps may be nested inside each other.
+pub const MAX_NESTING: usize = 32;
+
+/// Parsed arguments, keyword names borrow from the input unless the result was made owned.
+///
+/// Values are always owned, a [`MarinValue::String`] holds a `String` even when it could borrow from the input.
+/// Quoted strings with escapes have to be decoded into a new string anyway, and a lifetime on `MarinValue` would
+/// spread to every place that keeps values beyond the input, like the defaults and allowed values of a
+/// [`Schema`] and the typed conversions.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Marin<'a> {
     pub args: Vec<MarinValue>,
-    pub kwargs: HashMap<&'a str, MarinValue>,
+    /// In the order the keywords first appear.
+    pub kwargs: IndexMap<Cow<'a, str>, MarinValue>,
 }
 
+/// A [`Marin`] that does not borrow from the input and can be stored or sent to other threads.
+pub type OwnedMarin = Marin<'static>;
+
 impl Marin<'_> {
-    pub fn parse(string: &str) -> Result<Marin> {
-        let mut args: Vec<MarinValue> = vec![];
-        let mut kwargs: HashMap<&str, MarinValue> = HashMap::new();
+    pub fn parse(string: &str) -> Result<Marin<'_>> {
+        Self::parse_with(string, &ParseOptions::default())
+    }
+
+    pub fn parse_with<'a>(string: &'a str, options: &ParseOptions) -> Result<Marin<'a>> {
+        Self::parse_spanned_with(string, options)?.into_marin(options.duplicates)
+    }
+
+    /// Like [`Marin::parse`], also returning warnings about input that is allowed but probably a mistake.
+    pub fn parse_checked(string: &str) -> Result<(Marin<'_>, Vec<Warning>)> {
+        Self::parse_checked_with(string, &ParseOptions::default())
+    }
+
+    pub fn parse_checked_with<'a>(string: &'a str, options: &ParseOptions) -> Result<(Marin<'a>, Vec<Warning>)> {
+        let spanned = Self::parse_spanned_with(string, options)?;
+        let warnings = warning::check(&spanned, options);
+        Ok((spanned.into_marin(options.duplicates)?, warnings))
+    }
+
+    /// Read all of `reader` and parse it, the result does not borrow from the input.
+    pub fn parse_reader(mut reader: impl Read) -> Result<OwnedMarin> {
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+        Ok(Marin::parse(&input)?.into_owned())
+    }
+
+    pub fn parse_file(path: impl AsRef<Path>) -> Result<OwnedMarin> {
+        Self::parse_reader(File::open(path)?)
+    }
+
+    /// Parse a script of commands separated by newlines or `;`, each with its own result.
+    ///
+    /// Blank lines and comments are skipped, a command can not span several lines. A `;` inside a string,
+    /// list or map does not end a command.
+    pub fn parse_documents(input: &str) -> Vec<Document> {
+        Self::parse_documents_with(input, &ParseOptions::default())
+    }
+
+    /// Like [`Marin::parse_documents`], if `;` is in [`ParseOptions::punctuation`] it is part of words and only
+    /// newlines separate commands.
+    pub fn parse_documents_with(input: &str, options: &ParseOptions) -> Vec<Document> {
+        document::parse_documents(input, options)
+    }
+
+    /// Like [`Marin::parse_documents`], only failing if the file can not be read.
+    pub fn parse_documents_file(path: impl AsRef<Path>) -> Result<Vec<Document>> {
+        let input = std::fs::read_to_string(path)?;
+        Ok(Self::parse_documents(&input))
+    }
+
+    /// Parse as much of `input` as possible and report every problem instead of stopping at the first.
+    ///
+    /// Bad parts like an unterminated string or a stray `]` are skipped, the rest is parsed as usual.
+    pub fn parse_recovering(input: &str) -> Recovered {
+        Self::parse_recovering_with(input, &ParseOptions::default())
+    }
+
+    pub fn parse_recovering_with(input: &str, options: &ParseOptions) -> Recovered {
+        recover::parse_recovering(input, options)
+    }
+
+    /// Like [`Marin::parse`] but keeps the span of every argument, key and value.
+    ///
+    /// Arguments stay in source order with positionals, keywords and flags interleaved,
+    /// repeated keywords are all kept.
+    pub fn parse_spanned(string: &str) -> Result<SpannedMarin<'_>> {
+        Self::parse_spanned_with(string, &ParseOptions::default())
+    }
+
+    pub fn parse_spanned_with<'a>(string: &'a str, options: &ParseOptions) -> Result<SpannedMarin<'a>> {
+        let mut arguments = vec![];
+        let mut flags = HashMap::new();
 
         if string.is_empty() {
-            return Ok(Marin { args, kwargs });
+            return Ok(SpannedMarin { arguments });
         };
 
+        check_nesting(string)?;
         let pairs = MarinParser::parse(Rule::Marin, string)?;
+        check_punctuation(&pairs, options)?;
 
         if let Some(main_pair) = pairs.peek() {
             for pair in main_pair.into_inner() {
                 match pair.as_rule() {
                     Rule::Keyword => {
-                        let inner: Vec<_> = pair.into_inner().collect();
-                        let key = inner[0].as_str();
-                        let value = Self::serialize(&inner[1])?;
-                        kwargs.insert(key, value);
+                        let mut inner = pair.clone().into_inner();
+                        let key = child(&mut inner, &pair)?;
+                        let value = Self::keyword_value(inner.next(), &key, &pair, options)?;
+                        let key = Spanned::new(keyword_name(&key)?, key.as_span().into());
+                        arguments.push(SpannedArgument::Keyword { key, value });
                     }
                     Rule::Flag => {
-                        let inner = pair.into_inner().next().unwrap();
-                        let key = inner.as_str();
-                        kwargs.insert(key, MarinValue::Bool(true));
+                        let span = pair.as_span().into();
+                        let inner = child(&mut pair.clone().into_inner(), &pair)?;
+                        let key = Spanned::new(inner.as_str(), inner.as_span().into());
+                        let value = inner.as_rule() == Rule::FlagInner;
+                        match flags.insert(key.node, value) {
+                            Some(previous) if previous != value =>
+                                return Err(Error::ConflictingFlags { flag: key.node.into(), span }),
+                            _ => arguments.push(SpannedArgument::Flag { key, value, span }),
+                        }
+                    }
+                    Rule::Value | Rule::Number => {
+                        arguments.push(SpannedArgument::Positional(Self::serialize(&pair, options)?))
                     }
-                    Rule::Value | Rule::Number => args.push(Self::serialize(&pair)?),
                     Rule::EOI => (),
-                    _ => unreachable!()
+                    _ => return Err(unexpected(&pair)),
                 }
             };
-        } else {
-            return Ok(Marin { args, kwargs });
         }
 
-        Ok(Marin { args, kwargs })
+        Ok(SpannedMarin { arguments })
+    }
+
+    /// Whether the keyword argument `key` was explicitly cleared with `null` or a dangling `key:`.
+    pub fn is_null(&self, key: &str) -> bool {
+        self.kwargs.get(key) == Some(&MarinValue::Null)
+    }
+
+    /// The keyword argument `key` converted to `T`, `None` if it is absent.
+    ///
+    /// Use `Option<T>` for `T` to accept `null` as well.
+    pub fn get<T: FromMarinValue>(&self, key: &str) -> std::result::Result<Option<T>, ConversionError> {
+        self.kwargs.get(key).map(|value| args::convert(value).map_err(|e| e.with_key(key))).transpose()
+    }
+
+    pub fn get_int(&self, key: &str) -> std::result::Result<Option<i64>, ConversionError> {
+        self.get(key)
+    }
+
+    pub fn get_float(&self, key: &str) -> std::result::Result<Option<f64>, ConversionError> {
+        self.get(key)
+    }
+
+    pub fn get_str(&self, key: &str) -> std::result::Result<Option<&str>, ConversionError> {
+        match self.kwargs.get(key) {
+            Some(MarinValue::String(s)) => Ok(Some(s)),
+            Some(value) => Err(ConversionError { key: Some(key.into()), expected: "string".into(), found: value.kind() }),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether the flag `key` is set, an absent flag is `false`.
+    pub fn get_bool_flag(&self, key: &str) -> std::result::Result<bool, ConversionError> {
+        Ok(self.get(key)?.unwrap_or(false))
+    }
+
+    pub fn get_list_of<T: FromMarinValue>(&self, key: &str) -> std::result::Result<Option<Vec<T>>, ConversionError> {
+        self.get(key)
+    }
+
+    pub fn get_range(&self, key: &str) -> std::result::Result<Option<MarinRange>, ConversionError> {
+        self.get(key)
+    }
+
+    pub fn get_map_of<T: FromMarinValue>(&self, key: &str)
+                                         -> std::result::Result<Option<IndexMap<String, T>>, ConversionError> {
+        self.get(key)
+    }
+
+    pub fn get_duration(&self, key: &str) -> std::result::Result<Option<Duration>, ConversionError> {
+        self.get(key)
+    }
+
+    /// The positional argument at `index` converted to `T`, errors call it `#1` for index 0.
+    pub fn positional<T: FromMarinValue>(&self, index: usize) -> std::result::Result<Option<T>, ConversionError> {
+        self.args.get(index)
+            .map(|value| args::convert(value).map_err(|e| e.with_key(format!("#{}", index + 1))))
+            .transpose()
+    }
+
+    /// The value of a keyword or map entry, `value` is `None` if it was left out.
+    fn keyword_value(value: Option<Pair<Rule>>, key: &Pair<Rule>, entry: &Pair<Rule>, options: &ParseOptions)
+                     -> Result<SpannedValue> {
+        if let Some(value) = value {
+            return Self::serialize(&value, options);
+        }
+        // The entry also covers whitespace and comments after the colon.
+        let after_key = &entry.as_str()[key.as_span().end() - entry.as_span().start()..];
+        let colon_end = key.as_span().end() + after_key.find(':').map_or(0, |i| i + 1);
+        match options.dangling_keywords {
+            DanglingKeyword::Null =>
+                Ok(SpannedValue { kind: SpannedKind::Null, span: Span::new(colon_end, colon_end) }),
+            DanglingKeyword::Error => Err(Error::MissingValue {
+                key: key.as_str().into(),
+                span: Span::new(entry.as_span().start(), colon_end),
+            }),
+        }
     }
 
-    fn serialize(pair: &Pair<Rule>) -> Result<MarinValue> {
-        let val = match pair.as_rule() {
+    fn serialize(pair: &Pair<Rule>, options: &ParseOptions) -> Result<SpannedValue> {
+        let span = pair.as_span().into();
+        let kind = match pair.as_rule() {
             Rule::Value => {
-                let inner: Vec<_> = pair.clone().into_inner().collect();
-                Self::serialize(&inner[0])?
+                return Self::serialize(&child(&mut pair.clone().into_inner(), pair)?, options);
             }
             Rule::RangeExpr => {
-                let inner = pair.clone().into_inner().next().unwrap();
-                match inner.as_rule() {
-                    Rule::Range => {
-                        let values: Vec<_> = inner.into_inner().collect();
-                        MarinValue::Range(Range {
-                            start: values[0].as_str().parse().unwrap(),
-                            end: values[1].as_str().parse().unwrap(),
-                        })
-                    }
-                    Rule::RangeTo => {
-                        let to = inner.into_inner().next().unwrap();
-                        MarinValue::Range(Range {
-                            start: 0,
-                            end: to.as_str().parse().unwrap(),
-                        })
+                let (mut start, mut end, mut inclusive, mut step) = (None, None, false, None);
+                for part in pair.clone().into_inner() {
+                    match part.as_rule() {
+                        Rule::RangeStart => start = Some(range_bound(&part)?),
+                        Rule::RangeEnd => end = Some(range_bound(&part)?),
+                        Rule::RangeOp => inclusive = part.as_str() == "..=",
+                        Rule::RangeStep => step = Some(range_step(&part)?),
+                        _ => return Err(unexpected(&part)),
                     }
-                    _ => unreachable!()
                 }
+                SpannedKind::Range { start, end, inclusive, step }
+            }
+            Rule::Duration if !options.durations || !marin_value::is_ordered_duration(pair.as_str()) => {
+                SpannedKind::String(pair.as_str().into())
+            }
+            Rule::Duration => match marin_value::parse_duration(pair.as_str()) {
+                Some(duration) => SpannedKind::Duration(duration),
+                None => return Err(Error::DurationOverflow { literal: pair.as_str().into(), span }),
+            },
+            Rule::Float => {
+                match pair.as_str().parse::<f64>() {
+                    Ok(f) if f.is_finite() => SpannedKind::Float(f),
+                    _ => return Err(Error::InvalidFloat { literal: pair.as_str().into(), span }),
+                }
+            }
+            Rule::Int | Rule::Number => SpannedKind::Int(int(pair)?),
+            Rule::Null => SpannedKind::Null,
+            Rule::Bool => {
+                SpannedKind::Bool(pair.as_str().to_lowercase().parse().map_err(|_| unexpected(pair))?)
             }
-            Rule::Float => MarinValue::Float(pair.as_str().parse().unwrap()),
-            Rule::Int | Rule::Number => MarinValue::Int(pair.as_str().parse().unwrap()),
-            Rule::Bool => MarinValue::Bool(pair.as_str().to_lowercase().parse().unwrap()),
             Rule::String => {
-                let inner = pair.clone().into_inner().next().unwrap();
+                let inner = child(&mut pair.clone().into_inner(), pair)?;
                 match inner.as_rule() {
-                    Rule::Inner => MarinValue::String(inner.as_str().replace("\\\"", "\"")),
-                    Rule::Bareword => MarinValue::String(inner.as_str().into()),
-                    _ => unreachable!(),
+                    Rule::Inner | Rule::SingleInner => SpannedKind::String(unescape(&inner)?),
+                    Rule::Bareword => SpannedKind::String(inner.as_str().into()),
+                    _ => return Err(unexpected(&inner)),
                 }
             }
             Rule::List => {
-                let list: Vec<MarinValue> = pair.clone().into_inner()
-                                                .map(|v| Self::serialize(&v))
-                                                .collect::<Result<Vec<MarinValue>>>()?;
-                MarinValue::List(list)
+                let list: Vec<SpannedValue> = pair.clone().into_inner()
+                                                  .map(|v| Self::serialize(&v, options))
+                                                  .collect::<Result<Vec<SpannedValue>>>()?;
+                SpannedKind::List(list)
+            }
+            Rule::Map => {
+                let mut entries = vec![];
+                for entry in pair.clone().into_inner() {
+                    let mut inner = entry.clone().into_inner();
+                    let key = child(&mut inner, &entry)?;
+                    let value = Self::keyword_value(inner.next(), &key, &entry, options)?;
+                    let key = match Self::serialize(&key, options)?.kind {
+                        SpannedKind::String(s) => Spanned::new(s, key.as_span().into()),
+                        _ => return Err(unexpected(&key)),
+                    };
+                    entries.push((key, value));
+                }
+                SpannedKind::Map(entries)
+            }
+            _ => return Err(unexpected(pair)),
+        };
+        Ok(SpannedValue { kind, span })
+    }
+}
+
+/// Reject lists and maps nested deeper than [`MAX_NESTING`] before they can exhaust the stack.
+fn check_nesting(string: &str) -> Result<()> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in string.char_indices() {
+        let in_string = quote.is_some();
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            c if quote == Some(c) => quote = None,
+            '"' | '\'' if !in_string => quote = Some(c),
+            '[' | '{' if !in_string => {
+                depth += 1;
+                if depth > MAX_NESTING {
+                    return Err(Error::NestingTooDeep { span: Span::new(i, i + 1) });
+                }
             }
-            _ => unreachable!()
+            ']' | '}' if !in_string && depth > 0 => depth -= 1,
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Reject words with punctuation that is not in [`ParseOptions::punctuation`].
+fn check_punctuation(pairs: &Pairs<Rule>, options: &ParseOptions) -> Result<()> {
+    for pair in pairs.clone().flatten() {
+        if let Rule::Bareword | Rule::FlagInner | Rule::NegatedFlag = pair.as_rule() {
+            if let Some((offset, character)) = disallowed_punctuation(pair.as_str(), &options.punctuation) {
+                let start = pair.as_span().start() + offset;
+                let span = Span::new(start, start + character.len_utf8());
+                return Err(Error::DisallowedPunctuation { character, span });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Resolve the escape sequences of a quoted string.
+fn unescape(inner: &Pair<Rule>) -> Result<String> {
+    let text = inner.as_str();
+    let mut string = String::with_capacity(text.len());
+    let mut chars = text.char_indices();
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            string.push(c);
+            continue;
+        }
+        let decoded = match chars.next() {
+            Some((_, 'n')) => Some('\n'),
+            Some((_, 't')) => Some('\t'),
+            Some((_, 'r')) => Some('\r'),
+            Some((_, c @ '\\')) | Some((_, c @ '"')) | Some((_, c @ '\'')) => Some(c),
+            Some((_, 'u')) => unicode_escape(&mut chars),
+            _ => None,
         };
-        Ok(val)
+        match decoded {
+            Some(c) => string.push(c),
+            None => {
+                let end = chars.next().map_or(text.len(), |(end, _)| end);
+                let offset = inner.as_span().start();
+                return Err(Error::InvalidEscape {
+                    escape: text[start..end].into(),
+                    span: Span::new(offset + start, offset + end),
+                });
+            }
+        }
     }
+    Ok(string)
 }
 
-impl Display for Marin<'_> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        writeln!(f, "Arguments:")?;
-        for arg in &self.args {
-            writeln!(f, "    {:?}", arg)?;
+/// The character of a `\u{XXXX}` escape, `chars` starts after the `u`.
+fn unicode_escape(chars: &mut std::str::CharIndices) -> Option<char> {
+    if chars.next()?.1 != '{' {
+        return None;
+    }
+    let mut code = String::new();
+    loop {
+        match chars.next()?.1 {
+            '}' => break,
+            c if c.is_ascii_hexdigit() && code.len() < 6 => code.push(c),
+            _ => return None,
         }
-        writeln!(f, "Keyword Arguments:")?;
+    }
+    char::from_u32(u32::from_str_radix(&code, 16).ok()?)
+}
 
-        for (key, value) in self.kwargs.iter() {
-            writeln!(f, "    {}: {:?}", key, value)?;
+/// The name of a keyword, borrowed from the input unless it contains escapes.
+fn keyword_name<'a>(key: &Pair<'a, Rule>) -> Result<Cow<'a, str>> {
+    let inner = child(&mut key.clone().into_inner(), key)?;
+    match inner.as_rule() {
+        Rule::Inner | Rule::SingleInner if inner.as_str().contains('\\') => Ok(Cow::Owned(unescape(&inner)?)),
+        Rule::Inner | Rule::SingleInner | Rule::Bareword => Ok(Cow::Borrowed(inner.as_str())),
+        _ => Err(unexpected(&inner)),
+    }
+}
+
+/// The next child of `parent`, failing instead of panicking if the tree is not shaped as expected.
+fn child<'i>(pairs: &mut Pairs<'i, Rule>, parent: &Pair<'i, Rule>) -> Result<Pair<'i, Rule>> {
+    pairs.next().ok_or_else(|| unexpected(parent))
+}
+
+fn unexpected(pair: &Pair<Rule>) -> Error {
+    let message = format!("unexpected {}", pair.as_rule().description());
+    pest::error::Error::new_from_span(ErrorVariant::CustomError { message }, pair.as_span()).into()
+}
+
+fn int(pair: &Pair<Rule>) -> Result<i64> {
+    pair.as_str().parse().map_err(|_| Error::IntegerOverflow {
+        literal: pair.as_str().into(),
+        span: pair.as_span().into(),
+    })
+}
+
+fn range_bound(pair: &Pair<Rule>) -> Result<Spanned<i64>> {
+    let span = pair.as_span().into();
+    match int(pair) {
+        Ok(i) => Ok(Spanned::new(i, span)),
+        Err(_) => Err(Error::MalformedRange { literal: pair.as_str().into(), span }),
+    }
+}
+
+fn range_step(pair: &Pair<Rule>) -> Result<Spanned<i64>> {
+    let step = range_bound(pair)?;
+    if step.node <= 0 {
+        return Err(Error::InvalidStep { literal: pair.as_str().into(), span: step.span });
+    }
+    Ok(step)
+}
+
+impl<'a> Marin<'a> {
+    /// Copy the borrowed keyword names so the result no longer depends on the input.
+    pub fn into_owned(self) -> OwnedMarin {
+        Marin {
+            args: self.args,
+            kwargs: self.kwargs.into_iter().map(|(key, value)| (Cow::Owned(key.into_owned()), value)).collect(),
+        }
+    }
+}
+
+#[allow(dead_code)]
+struct DisplayPair<'i, R: pest::RuleType>(pest::iterators::Pair<'i, R>);
+
+impl<'i, R: pest::RuleType> fmt::Display for DisplayPair<'i, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.display(f, 0)
+    }
+}
+
+#[allow(dead_code)]
+impl<'i, R: pest::RuleType> DisplayPair<'i, R> {
+    fn display(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        let span = self.0.clone().as_span();
+        let rule = self.0.as_rule();
+        let inner = self.0.clone().into_inner();
+        let indent = "    ".repeat(depth);
+        let children_possible = if let Some(len) = inner.size_hint().1 {
+            len > 0
+        } else {
+            true
+        };
+
+        write!(f, "{}{:?}({}", indent, rule, span.as_str())?;
+        if children_possible {
+            writeln!(f, ", [")?;
+            for pair in self.0.clone().into_inner() {
+                DisplayPair(pair).display(f, depth + 1)?;
+            }
+            write!(f, "{}]),", indent)?;
+        } else {
+            write!(f, ")")?;
+        }
+        if depth > 0 {
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -131,22 +538,22 @@ impl Display for Marin<'_> {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::borrow::Cow;
+    use std::convert::TryFrom;
 
-    use crate::{Marin, MarinValue};
+    use indexmap::IndexMap;
+
+    use crate::{
+        ConversionError, DanglingKeyword, DuplicateKeywords, Error, Marin, MarinRange, MarinValue, MAX_NESTING, ParseOptions,
+        Span, ValueKind,
+    };
     use crate::MarinValue::*;
     use crate::Result;
 
-    impl Marin<'_> {
-        pub fn test(string: &str, expected: Marin) -> Result<()> {
-            Ok(())
-        }
-    }
-
     #[test]
     fn empty() -> Result<()> {
         let m = Marin::parse("")?;
-        assert_eq!(m, Marin { args: vec![], kwargs: HashMap::new() });
+        assert_eq!(m, Marin { args: vec![], kwargs: IndexMap::new() });
         Ok(())
     }
 
@@ -155,17 +562,31 @@ mod tests {
         let m = Marin::parse("\"t e\\\" s t\"")?;
         assert_eq!(m, Marin {
             args: vec!["t e\" s t".into()],
-            kwargs: HashMap::new(),
+            kwargs: IndexMap::new(),
         });
         Ok(())
     }
 
+    #[test]
+    fn quoted_string_keeps_whitespace_and_hashes() -> Result<()> {
+        let m = Marin::parse("\"  #1 \"")?;
+        assert_eq!(m.args, vec![MarinValue::from("  #1 ")]);
+        Ok(())
+    }
+
+    #[test]
+    fn string_with_escaped_backslash() -> Result<()> {
+        let m = Marin::parse("\"C:\\\\Users \\\"x\\\"\"")?;
+        assert_eq!(m.args, vec![MarinValue::from("C:\\Users \"x\"")]);
+        Ok(())
+    }
+
     #[test]
     fn flags() -> Result<()> {
         let m = Marin::parse("-overwrite -dynamic")?;
-        let mut kwargs: HashMap<&str, MarinValue> = HashMap::new();
-        kwargs.insert("overwrite", true.into());
-        kwargs.insert("dynamic", true.into());
+        let mut kwargs: IndexMap<Cow<str>, MarinValue> = IndexMap::new();
+        kwargs.insert("overwrite".into(), true.into());
+        kwargs.insert("dynamic".into(), true.into());
         assert_eq!(m, Marin { args: vec![], kwargs });
         Ok(())
     }
@@ -173,9 +594,9 @@ mod tests {
     #[test]
     fn flags_and_keywords() -> Result<()> {
         let m = Marin::parse("-overwrite offset: 30m")?;
-        let mut kwargs: HashMap<&str, MarinValue> = HashMap::new();
-        kwargs.insert("overwrite", true.into());
-        kwargs.insert("offset", "30m".into());
+        let mut kwargs: IndexMap<Cow<str>, MarinValue> = IndexMap::new();
+        kwargs.insert("overwrite".into(), true.into());
+        kwargs.insert("offset".into(), Duration(std::time::Duration::from_secs(30 * 60)));
         assert_eq!(m, Marin { args: vec![], kwargs });
         Ok(())
     }
@@ -183,9 +604,9 @@ mod tests {
     #[test]
     fn positional_int_and_flags() -> Result<()> {
         let m = Marin::parse("777000 -mention -id")?;
-        let kwargs: HashMap<&str, MarinValue> = vec![
-            ("mention", true.into()),
-            ("id", true.into()),
+        let kwargs: IndexMap<Cow<str>, MarinValue> = vec![
+            ("mention".into(), true.into()),
+            ("id".into(), true.into()),
         ].into_iter().collect();
         assert_eq!(m, Marin { args: vec![777000.into()], kwargs });
         Ok(())
@@ -194,22 +615,22 @@ mod tests {
     #[test]
     fn positional_username() -> Result<()> {
         let m = Marin::parse("@username")?;
-        assert_eq!(m, Marin { args: vec!["@username".into()], kwargs: HashMap::new() });
+        assert_eq!(m, Marin { args: vec!["@username".into()], kwargs: IndexMap::new() });
         Ok(())
     }
 
     #[test]
     fn invite_link() -> Result<()> {
         let m = Marin::parse("https://t.me/joinchat/CkzknkNYuLsKbTc91GfhGw")?;
-        assert_eq!(m, Marin { args: vec!["https://t.me/joinchat/CkzknkNYuLsKbTc91GfhGw".into()], kwargs: HashMap::new() });
+        assert_eq!(m, Marin { args: vec!["https://t.me/joinchat/CkzknkNYuLsKbTc91GfhGw".into()], kwargs: IndexMap::new() });
         Ok(())
     }
 
     #[test]
     fn quoted_key_word_argument() -> Result<()> {
         let m = Marin::parse("reason: \"spam[gban]\"")?;
-        let kwargs: HashMap<&str, MarinValue> = vec![
-            ("reason", "spam[gban]".into()),
+        let kwargs: IndexMap<Cow<str>, MarinValue> = vec![
+            ("reason".into(), "spam[gban]".into()),
         ].into_iter().collect();
         assert_eq!(m, Marin { args: vec![], kwargs });
         Ok(())
@@ -218,8 +639,8 @@ mod tests {
     #[test]
     fn wildcard_keyword_argument() -> Result<()> {
         let m = Marin::parse("reason: \"Kriminalamt *\"")?;
-        let kwargs: HashMap<&str, MarinValue> = vec![
-            ("reason", "Kriminalamt *".into()),
+        let kwargs: IndexMap<Cow<str>, MarinValue> = vec![
+            ("reason".into(), "Kriminalamt *".into()),
         ].into_iter().collect();
         assert_eq!(m, Marin { args: vec![], kwargs });
         Ok(())
@@ -228,8 +649,8 @@ mod tests {
     #[test]
     fn keyword_with_link() -> Result<()> {
         let m = Marin::parse("777000 \"ban reason\" link: https://t.me/c/1129887931/26708")?;
-        let kwargs: HashMap<&str, MarinValue> = vec![
-            ("link", "https://t.me/c/1129887931/26708".into()),
+        let kwargs: IndexMap<Cow<str>, MarinValue> = vec![
+            ("link".into(), "https://t.me/c/1129887931/26708".into()),
         ].into_iter().collect();
         assert_eq!(m, Marin {
             args: vec![777000.into(), "ban reason".into()],
@@ -241,9 +662,9 @@ mod tests {
     #[test]
     fn chat_id_with_flags() -> Result<()> {
         let m = Marin::parse("-1001129887931 -strafanzeige polizei: exclude")?;
-        let kwargs: HashMap<&str, MarinValue> = vec![
-            ("strafanzeige", true.into()),
-            ("polizei", "exclude".into()),
+        let kwargs: IndexMap<Cow<str>, MarinValue> = vec![
+            ("strafanzeige".into(), true.into()),
+            ("polizei".into(), "exclude".into()),
         ].into_iter().collect();
         assert_eq!(m, Marin {
             args: vec![Int(-1001129887931)],
@@ -255,8 +676,8 @@ mod tests {
     #[test]
     fn list_of_ids() -> Result<()> {
         let m = Marin::parse("chats: [-1001129887931, -1001367463001]")?;
-        let kwargs: HashMap<&str, MarinValue> = vec![
-            ("chats", vec![Int(-1001129887931), Int(-1001367463001)].into()),
+        let kwargs: IndexMap<Cow<str>, MarinValue> = vec![
+            ("chats".into(), vec![Int(-1001129887931), Int(-1001367463001)].into()),
         ].into_iter().collect();
         assert_eq!(m, Marin { args: vec![], kwargs });
         Ok(())
@@ -271,8 +692,8 @@ mod tests {
                 "arg2".into(),
                 "arg3".into(),
                 "4arg".into(),
-            ].into(),
-            kwargs: HashMap::new(),
+            ],
+            kwargs: IndexMap::new(),
         });
         Ok(())
     }
@@ -280,9 +701,9 @@ mod tests {
     #[test]
     fn keyword_arguments() -> Result<()> {
         let m = Marin::parse("arg1: val1 arg2: \"val2.1 val2.2\"")?;
-        let kwargs: HashMap<&str, MarinValue> = vec![
-            ("arg1", "val1".into()),
-            ("arg2", "val2.1 val2.2".into()),
+        let kwargs: IndexMap<Cow<str>, MarinValue> = vec![
+            ("arg1".into(), "val1".into()),
+            ("arg2".into(), "val2.1 val2.2".into()),
         ].into_iter().collect();
         assert_eq!(m, Marin { args: vec![], kwargs });
         Ok(())
@@ -291,9 +712,9 @@ mod tests {
     #[test]
     fn keyword_with_lists() -> Result<()> {
         let m = Marin::parse("arg: [123, 456] arg2: [\"abc\", \"de f\", \"xyz\"]")?;
-        let kwargs: HashMap<&str, MarinValue> = vec![
-            ("arg", vec![123.into(), 456.into()].into()),
-            ("arg2", vec!["abc".into(), "de f".into(), "xyz".into()].into()),
+        let kwargs: IndexMap<Cow<str>, MarinValue> = vec![
+            ("arg".into(), vec![123.into(), 456.into()].into()),
+            ("arg2".into(), vec!["abc".into(), "de f".into(), "xyz".into()].into()),
         ].into_iter().collect();
         assert_eq!(m, Marin { args: vec![], kwargs });
         Ok(())
@@ -302,10 +723,10 @@ mod tests {
     #[test]
     fn keyword_with_ranges() -> Result<()> {
         let m = Marin::parse("arg: 1..10 arg2: -5..5 arg3: -10..0")?;
-        let kwargs: HashMap<&str, MarinValue> = vec![
-            ("arg", Range(1..10)),
-            ("arg2", Range(-5..5)),
-            ("arg3", Range(-10..0)),
+        let kwargs: IndexMap<Cow<str>, MarinValue> = vec![
+            ("arg".into(), Range(MarinRange::new(1, 10))),
+            ("arg2".into(), Range(MarinRange::new(-5, 5))),
+            ("arg3".into(), Range(MarinRange::new(-10, 0))),
         ].into_iter().collect();
         assert_eq!(m, Marin { args: vec![], kwargs });
         Ok(())
@@ -314,62 +735,451 @@ mod tests {
     #[test]
     fn scientific_notation() -> Result<()> {
         let m = Marin::parse("1e4 2.5e4 125e-5")?;
-        assert_eq!(m, Marin { args: vec![Float(10000.0), Float(25000.0), Float(0.00125)], kwargs: HashMap::new() });
+        assert_eq!(m, Marin { args: vec![Float(10000.0), Float(25000.0), Float(0.00125)], kwargs: IndexMap::new() });
         Ok(())
     }
 
     #[test]
     fn duration_expression() -> Result<()> {
-        let m = Marin::parse("2w3d3h5s")?;
+        let m = Marin::parse("2w3d3h5s offset: 30m timeout: 1m30s500ms")?;
+        let mut kwargs: IndexMap<Cow<str>, MarinValue> = IndexMap::new();
+        kwargs.insert("offset".into(), Duration(std::time::Duration::from_secs(30 * 60)));
+        kwargs.insert("timeout".into(), Duration(std::time::Duration::from_millis(90_500)));
         assert_eq!(m, Marin {
-            args: vec!["2w3d3h5s".into()],
-            kwargs: HashMap::new(),
+            args: vec![Duration(std::time::Duration::from_secs(((2 * 7 + 3) * 24 + 3) * 3600 + 5))],
+            kwargs,
         });
+        assert_eq!(m.get_duration("offset")?, Some(std::time::Duration::from_secs(1800)));
         Ok(())
     }
 
     #[test]
-    #[should_panic]
-    fn flag_keyword() {
-        let m = Marin::parse("-flag: 123").unwrap();
-        ()
+    fn sub_millisecond_durations() -> Result<()> {
+        let m = Marin::parse("1ms500us 250ns")?;
+        assert_eq!(m.args, vec![
+            Duration(std::time::Duration::from_micros(1500)), Duration(std::time::Duration::from_nanos(250)),
+        ]);
+        assert_eq!(m.to_string(), "1ms500us 250ns");
+        Ok(())
     }
-}
 
+    #[test]
+    fn not_a_duration() -> Result<()> {
+        let m = Marin::parse("5s2h 30 3x 30ms")?;
+        assert_eq!(m.args, vec![
+            "5s2h".into(), Int(30), "3x".into(), Duration(std::time::Duration::from_millis(30)),
+        ]);
+        Ok(())
+    }
 
-struct DisplayPair<'i, R: pest::RuleType>(pest::iterators::Pair<'i, R>);
+    #[test]
+    fn raw_durations() -> Result<()> {
+        let options = ParseOptions { durations: false, ..ParseOptions::default() };
+        let mut kwargs: IndexMap<Cow<str>, MarinValue> = IndexMap::new();
+        kwargs.insert("offset".into(), "30m".into());
+        assert_eq!(Marin::parse_with("2w3d offset: 30m", &options)?, Marin { args: vec!["2w3d".into()], kwargs });
+        Ok(())
+    }
 
-impl<'i, R: pest::RuleType> fmt::Display for DisplayPair<'i, R> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.display(f, 0)
+    #[test]
+    fn duration_overflow() {
+        match Marin::parse("99999999999999999w") {
+            Err(Error::DurationOverflow { literal, span }) => {
+                assert_eq!(literal, "99999999999999999w");
+                assert_eq!(span, Span::new(0, 18));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
     }
-}
 
-impl<'i, R: pest::RuleType> DisplayPair<'i, R> {
-    fn display(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
-        let span = self.0.clone().as_span();
-        let rule = self.0.as_rule();
-        let inner = self.0.clone().into_inner();
-        let indent = "    ".repeat(depth);
-        let children_possible = if let Some(len) = inner.size_hint().1 {
-            len > 0
-        } else {
-            true
+    #[test]
+    fn integer_overflow() {
+        match Marin::parse("1 99999999999999999999") {
+            Err(Error::IntegerOverflow { literal, span }) => {
+                assert_eq!(literal, "99999999999999999999");
+                assert_eq!(span, Span::new(2, 22));
+            }
+            other => panic!("expected an overflow, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn open_inclusive_and_stepped_ranges() -> Result<()> {
+        let m = Marin::parse("3.. ..=5 1..=5 0..100:10 ids: ..10")?;
+        assert_eq!(m.args, vec![
+            Range(MarinRange::from(3..)),
+            Range(MarinRange::from(..=5)),
+            Range(MarinRange::inclusive(1, 5)),
+            Range(MarinRange::new(0, 100).with_step(10)),
+        ]);
+        assert_eq!(m.kwargs["ids"], Range(MarinRange::from(..10)));
+        assert_eq!(m.positional::<std::ops::RangeInclusive<i64>>(2)?, Some(1..=5));
+        Ok(())
+    }
+
+    #[test]
+    fn maps() -> Result<()> {
+        let m = Marin::parse("overrides: {\"-100123\": {mode: kick, limit: 5}, other: [1, 2], empty: {}} {}")?;
+        let mut chat = IndexMap::new();
+        chat.insert("mode".to_string(), MarinValue::from("kick"));
+        chat.insert("limit".to_string(), Int(5));
+        let mut overrides = IndexMap::new();
+        overrides.insert("-100123".to_string(), Map(chat));
+        overrides.insert("other".to_string(), List(vec![Int(1), Int(2)]));
+        overrides.insert("empty".to_string(), Map(IndexMap::new()));
+        assert_eq!(m.kwargs["overrides"], Map(overrides));
+        assert_eq!(m.args, vec![Map(IndexMap::new())]);
+        let keys: Vec<_> = m.get_map_of::<MarinValue>("overrides")?.unwrap().into_keys().collect();
+        assert_eq!(keys, vec!["-100123", "other", "empty"]);
+        Ok(())
+    }
+
+    #[test]
+    fn map_repeated_key() -> Result<()> {
+        let m = Marin::parse("{a: 1, b: 2, a: 3}")?;
+        assert_eq!(m.positional::<std::collections::BTreeMap<std::string::String, i64>>(0)?.unwrap(),
+                   vec![("a".to_string(), 3), ("b".to_string(), 2)].into_iter().collect());
+        assert_eq!(m.positional::<IndexMap<std::string::String, i64>>(0)?.unwrap().keys().collect::<Vec<_>>(),
+                   vec!["a", "b"]);
+        Ok(())
+    }
+
+    #[test]
+    fn map_nesting_limit() {
+        let input = format!("{}{}", "{a: ".repeat(MAX_NESTING + 1), "}".repeat(MAX_NESTING + 1));
+        assert!(matches!(Marin::parse(&input), Err(Error::NestingTooDeep { .. })));
+    }
+
+    #[test]
+    fn null_literals() -> Result<()> {
+        let m = Marin::parse("null none None nonetheless reason: none chats: [1, null]")?;
+        assert_eq!(m.args, vec![Null, Null, Null, "nonetheless".into()]);
+        assert!(m.is_null("reason"));
+        assert!(!m.is_null("missing"));
+        assert_eq!(m.get::<Option<std::string::String>>("reason")?, Some(None));
+        assert_eq!(m.get::<Option<std::string::String>>("missing")?, None);
+        assert_eq!(m.get_str("reason").unwrap_err().to_string(), "`reason`: expected string, found null");
+        assert_eq!(m.get_list_of::<Option<i64>>("chats")?, Some(vec![Some(1), None]));
+        Ok(())
+    }
+
+    #[test]
+    fn dangling_keyword() -> Result<()> {
+        let input = "777000 reason: -silent limit:";
+        match Marin::parse(input) {
+            Err(Error::MissingValue { key, span }) => {
+                assert_eq!(key, "reason");
+                assert_eq!(span.as_str(input), "reason:");
+            }
+            other => panic!("expected a missing value, got {:?}", other),
+        }
+        let error = Marin::parse(input).unwrap_err();
+        assert_eq!(error.to_diagnostic(input).unwrap().to_string(),
+                   "keyword `reason` has no value at line 1, column 8\n\
+                    777000 reason: -silent limit:\n       ^^^^^^^");
+
+        let options = ParseOptions { dangling_keywords: DanglingKeyword::Null, ..ParseOptions::default() };
+        let m = Marin::parse_with(input, &options)?;
+        assert!(m.is_null("reason") && m.is_null("limit"));
+        assert_eq!(m.kwargs["silent"], Bool(true));
+        assert_eq!(Marin::parse_with("{a:, b: 1}", &options)?.args[0].kind(), crate::ValueKind::Map);
+        Ok(())
+    }
+
+    #[test]
+    fn negated_flags() -> Result<()> {
+        let m = Marin::parse("-no-mention !silent +notify -nothing -no")?;
+        assert_eq!(m.kwargs["mention"], Bool(false));
+        assert_eq!(m.kwargs["silent"], Bool(false));
+        assert_eq!(m.kwargs["notify"], Bool(true));
+        assert_eq!(m.kwargs["nothing"], Bool(true));
+        assert_eq!(m.kwargs["no"], Bool(true));
+        assert_eq!(Marin::parse("-x -x !y -no-y")?.kwargs.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn empty_negated_flags() {
+        for input in &["-no-", "1 -no- 2", "-no-5", "!", "1 !"] {
+            assert!(matches!(Marin::parse(input), Err(Error::Parser(_))), "{} should not parse", input);
+        }
+        let d = Marin::parse("-no-").unwrap_err().to_diagnostic("-no-").unwrap();
+        assert_eq!(d.message, "unexpected end of input, expected flag name");
+    }
+
+    #[test]
+    fn conflicting_flags() {
+        let input = "-mention 1 -no-mention";
+        match Marin::parse(input) {
+            Err(Error::ConflictingFlags { flag, span }) => {
+                assert_eq!(flag, "mention");
+                assert_eq!(span.as_str(input), "-no-mention");
+            }
+            other => panic!("expected conflicting flags, got {:?}", other),
+        }
+        assert!(matches!(Marin::parse("!x +x"), Err(Error::ConflictingFlags { .. })));
+    }
+
+    #[test]
+    fn source_order() {
+        let m = Marin::parse("zeta: 1 -beta alpha: 2 gamma: 3").unwrap();
+        let keys: Vec<_> = m.kwargs.keys().map(|key| key.as_ref()).collect();
+        assert_eq!(keys, vec!["zeta", "beta", "alpha", "gamma"]);
+
+        let spanned = Marin::parse_spanned("1 key: 2 3 -flag").unwrap();
+        let spans: Vec<_> = spanned.arguments.iter().map(|a| a.span().as_str("1 key: 2 3 -flag")).collect();
+        assert_eq!(spans, vec!["1", "key: 2", "3", "-flag"]);
+    }
+
+    #[test]
+    fn owned() {
+        let m = Marin::parse("1 reason: spam -silent").unwrap();
+        assert!(m.kwargs.keys().all(|key| matches!(key, Cow::Borrowed(_))));
+
+        let input = std::string::String::from("1 reason: spam -silent");
+        let owned = Marin::parse(&input).unwrap().into_owned();
+        drop(input);
+        let sent = std::thread::spawn(move || owned.clone()).join().unwrap();
+        assert_eq!(sent, m);
+        assert_eq!(sent.get_str("reason"), Ok(Some("spam")));
+    }
+
+    #[test]
+    fn duplicate_keywords() {
+        let parse = |duplicates| {
+            let options = ParseOptions { duplicates, ..ParseOptions::default() };
+            Marin::parse_with("tag: a other: 1 tag: b tag: [c]", &options)
         };
+        assert_eq!(parse(DuplicateKeywords::LastWins).unwrap().kwargs["tag"], List(vec!["c".into()]));
+        assert_eq!(parse(DuplicateKeywords::FirstWins).unwrap().kwargs["tag"], "a".into());
+        assert_eq!(
+            parse(DuplicateKeywords::Accumulate).unwrap().kwargs["tag"],
+            List(vec!["a".into(), "b".into(), List(vec!["c".into()])])
+        );
+        match parse(DuplicateKeywords::Error) {
+            Err(Error::DuplicateKeyword { key, span }) => {
+                assert_eq!(key, "tag");
+                assert_eq!(span, Span::new(16, 22));
+            }
+            other => panic!("expected a duplicate keyword, got {:?}", other),
+        }
+        let m = parse(DuplicateKeywords::Accumulate).unwrap();
+        assert_eq!(m.kwargs.keys().map(|key| key.as_ref()).collect::<Vec<_>>(), vec!["tag", "other"]);
+    }
 
-        write!(f, "{}{:?}({}", indent, rule, span.as_str())?;
-        if children_possible {
-            writeln!(f, ", [")?;
-            for pair in self.0.clone().into_inner() {
-                DisplayPair(pair).display(f, depth + 1)?;
+    #[test]
+    fn duplicate_flags() {
+        let parse = |input, duplicates| {
+            let options = ParseOptions { duplicates, ..ParseOptions::default() };
+            Marin::parse_with(input, &options)
+        };
+        for duplicates in &[DuplicateKeywords::Error, DuplicateKeywords::Accumulate] {
+            assert_eq!(parse("-x 1 -x +x", *duplicates).unwrap().kwargs["x"], Bool(true));
+            assert_eq!(parse("!x -no-x", *duplicates).unwrap().kwargs["x"], Bool(false));
+        }
+
+        // A flag and a keyword with the same name are duplicates.
+        assert_eq!(parse("x: 1 -x", DuplicateKeywords::LastWins).unwrap().kwargs["x"], Bool(true));
+        assert_eq!(parse("x: 1 -x", DuplicateKeywords::FirstWins).unwrap().kwargs["x"], Int(1));
+        assert_eq!(parse("-x x: true", DuplicateKeywords::Accumulate).unwrap().kwargs["x"],
+                   List(vec![Bool(true), Bool(true)]));
+        match parse("x: 1 -x", DuplicateKeywords::Error) {
+            Err(Error::DuplicateKeyword { key, span }) => assert_eq!((key.as_str(), span), ("x", Span::new(5, 7))),
+            other => panic!("expected a duplicate keyword, got {:?}", other),
+        }
+        assert!(parse("-x x: true -x", DuplicateKeywords::Error).is_err());
+    }
+
+    #[test]
+    fn escapes() -> Result<()> {
+        let m = Marin::parse(r#"reason: "line\none\ttab \\ \"q\" \'s\' \u{1F980}""#)?;
+        assert_eq!(m.kwargs["reason"], "line\none\ttab \\ \"q\" 's' \u{1F980}".into());
+        assert_eq!(Marin::parse(r#""a\\""#)?.args, vec!["a\\".into()]);
+        let m = Marin::parse(r#"'it\'s "quoted"' 'key\n': 'x'"#)?;
+        assert_eq!(m.args, vec!["it's \"quoted\"".into()]);
+        assert_eq!(m.kwargs["key\n"], "x".into());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        for (input, escape) in &[(r#""a\qb""#, r"\q"), (r#"'\u{110000}'"#, r"\u{110000}"), (r#""\u41""#, r"\u4"),
+                                 (r#"k: "\u{}""#, r"\u{}")] {
+            match Marin::parse(input) {
+                Err(Error::InvalidEscape { escape: found, span }) => {
+                    assert_eq!(found, *escape);
+                    assert_eq!(span.as_str(input), *escape);
+                }
+                other => panic!("expected an invalid escape for {:?}, got {:?}", input, other),
             }
-            write!(f, "{}]),", indent)?;
-        } else {
-            write!(f, ")")?;
         }
-        if depth > 0 {
-            writeln!(f)?;
+        assert!(matches!(Marin::parse("'unterminated"), Err(Error::Parser(_))));
+    }
+
+    #[test]
+    fn unicode_barewords() -> Result<()> {
+        let m = Marin::parse("@user_name Betrüger #spam +4915112345678 🦀 👍🏽 50% 5-spam true_x \
+                              https://t.me/joinchat/AAA?start=a&b=1 reason: Spam-Bot -dry-run !pin-message")?;
+        let words: Vec<MarinValue> = vec![
+            "@user_name", "Betrüger", "#spam", "+4915112345678", "🦀", "👍🏽", "50%", "5-spam", "true_x",
+            "https://t.me/joinchat/AAA?start=a&b=1",
+        ].into_iter().map(MarinValue::from).collect();
+        assert_eq!(m.args, words);
+        assert_eq!(m.kwargs["reason"], "Spam-Bot".into());
+        assert_eq!(m.kwargs["dry-run"], Bool(true));
+        assert_eq!(m.kwargs["pin-message"], Bool(false));
+
+        let m = Marin::parse("[a,b] key:value 1 # a comment\n-5 +flag")?;
+        assert_eq!(m.args, vec![List(vec!["a".into(), "b".into()]), Int(1), Int(-5)]);
+        assert_eq!(m.kwargs["key"], "value".into());
+        assert_eq!(m.kwargs["flag"], Bool(true));
+        Ok(())
+    }
+
+    #[test]
+    fn disallowed_punctuation() {
+        match Marin::parse("reason: a~b") {
+            Err(Error::DisallowedPunctuation { character, span }) => {
+                assert_eq!(character, '~');
+                assert_eq!(span, Span::new(9, 10));
+            }
+            other => panic!("expected disallowed punctuation, got {:?}", other),
+        }
+        let options = ParseOptions { punctuation: "~".into(), ..ParseOptions::default() };
+        assert_eq!(Marin::parse_with("a~b", &options).unwrap().args, vec!["a~b".into()]);
+        assert!(matches!(Marin::parse_with("-dry-run", &options), Err(Error::DisallowedPunctuation { .. })));
+        assert_eq!(Marin::parse("\"a~b\"").unwrap().args, vec!["a~b".into()]);
+    }
+
+    #[test]
+    fn invalid_step() {
+        match Marin::parse("0..10:0") {
+            Err(Error::InvalidStep { literal, span }) => {
+                assert_eq!(literal, "0");
+                assert_eq!(span, Span::new(6, 7));
+            }
+            other => panic!("expected an invalid step, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn range_bound_overflow() {
+        match Marin::parse("ids: 1..9223372036854775808") {
+            Err(Error::MalformedRange { literal, span }) => {
+                assert_eq!(literal, "9223372036854775808");
+                assert_eq!(span, Span::new(8, 27));
+            }
+            other => panic!("expected a malformed range, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn float_overflow() {
+        match Marin::parse("1e999") {
+            Err(Error::InvalidFloat { literal, .. }) => assert_eq!(literal, "1e999"),
+            other => panic!("expected an invalid float, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn overflow_diagnostic() {
+        let input = "limit: 99999999999999999999";
+        let d = Marin::parse(input).unwrap_err().to_diagnostic(input).unwrap();
+        assert_eq!(d.column, 8);
+    }
+
+    #[test]
+    fn nesting_limit() -> Result<()> {
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        Marin::parse(&nested(MAX_NESTING))?;
+        match Marin::parse(&format!("kw: {}", nested(MAX_NESTING + 1))) {
+            Err(Error::NestingTooDeep { span }) => assert_eq!(span, Span::new(4 + MAX_NESTING, 5 + MAX_NESTING)),
+            other => panic!("expected nesting error, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn hostile_inputs_do_not_panic() {
+        let deep_list = "[".repeat(500) + &"]".repeat(500);
+        let unclosed_list = "[".repeat(500);
+        let inputs = [
+            "99999999999999999999",
+            "-99999999999999999999",
+            "9223372036854775807 -9223372036854775808",
+            "9223372036854775808",
+            "..99999999999999999999",
+            "-99999999999999999999..0",
+            "1e309 -1e309 1e-400",
+            "[1, 99999999999999999999]",
+            "kw: [[[[[[1]]]]]]",
+            "\"[[[[\" [\"\\\"[[[[\"]",
+            "\"", "\"\\", "\"\\\"", "[", "]", "[,]", ",", ":", "::", "-", "--", "-:", "..", "...", "1...2",
+            "..-", "1..-", "kw:", "kw: kw:", "kw: -flag", "\u{0}", "\n\n\t", "#", "# comment",
+            "ü", "🦀", "\"🦀\"", "@@@", "https://", "t.me/c/", "True: False", "-1e", "0x10", "00",
+            deep_list.as_str(),
+            unclosed_list.as_str(),
+        ];
+        for input in inputs.iter() {
+            let _ = Marin::parse(input);
+            let _ = Marin::parse_spanned(input);
+            if let Err(e) = Marin::parse(input) {
+                let _ = e.to_string();
+                let _ = e.to_diagnostic(input).map(|d| d.to_string());
+            }
         }
+    }
+
+    #[test]
+    fn typed_accessors() -> Result<()> {
+        let m = Marin::parse("777000 limit: 5 reason: spam chats: [1, 2] r: 1..5 -silent")?;
+        assert_eq!(m.get_int("limit")?, Some(5));
+        assert_eq!(m.get_float("limit")?, Some(5.0));
+        assert_eq!(m.get_str("reason")?, Some("spam"));
+        assert!(m.get_bool_flag("silent")?);
+        assert!(!m.get_bool_flag("loud")?);
+        assert_eq!(m.get_list_of::<i64>("chats")?, Some(vec![1, 2]));
+        assert_eq!(m.get_range("r")?, Some(MarinRange::new(1, 5)));
+        assert_eq!(m.get_int("missing")?, None);
+        assert_eq!(m.positional::<u32>(0)?, Some(777000));
+        assert_eq!(m.positional::<u32>(1)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn typed_accessor_errors() -> Result<()> {
+        let m = Marin::parse("spam limit: ten chats: [1, a]")?;
+        let error = m.get_int("limit").unwrap_err();
+        assert_eq!(error, ConversionError { key: Some("limit".into()), expected: "int".into(), found: ValueKind::String });
+        assert_eq!(error.to_string(), "`limit`: expected int, found string");
+        assert_eq!(m.get_list_of::<i64>("chats").unwrap_err().to_string(),
+                   "`chats`: expected list of ints, found list");
+        assert_eq!(m.get_str("chats").unwrap_err().found, ValueKind::List);
+        assert_eq!(m.positional::<i64>(0).unwrap_err().to_string(), "`#1`: expected int, found string");
+        assert!(matches!(Error::from(error), Error::Conversion(_)));
         Ok(())
     }
+
+    #[test]
+    fn try_from_value() {
+        assert_eq!(i64::try_from(&Int(3)), Ok(3));
+        assert_eq!(u8::try_from(&Int(300)).unwrap_err().expected, "int between 0 and 255");
+        assert_eq!(i16::try_from(&Int(-40000)).unwrap_err().expected, "int between -32768 and 32767");
+        assert_eq!(u64::try_from(&Int(-1)).unwrap_err().expected, "non-negative int");
+        assert_eq!(<i64 as crate::FromMarinValue>::expected(), "int");
+        assert_eq!(Vec::<u8>::try_from(&List(vec![Int(256)])).unwrap_err().expected, "list of ints between 0 and 255");
+        assert_eq!(<Vec<Option<i64>> as crate::FromMarinValue>::expected(), "list of ints or null");
+        assert_eq!(std::string::String::try_from(&MarinValue::from("a")), Ok("a".to_string()));
+        assert_eq!(Vec::<std::string::String>::try_from(&List(vec!["a".into()])), Ok(vec!["a".to_string()]));
+        assert_eq!(std::ops::Range::<i64>::try_from(&Range(MarinRange::new(1, 2))), Ok(1..2));
+        assert_eq!(std::ops::Range::<i64>::try_from(&Range(MarinRange::inclusive(1, 2))).unwrap_err().to_string(),
+                   "expected range like `a..b`, found range");
+        assert_eq!(bool::try_from(&Int(1)).unwrap_err().to_string(), "expected bool, found int");
+    }
+
+    #[test]
+    #[should_panic]
+    fn flag_keyword() {
+        Marin::parse("-flag: 123").unwrap();
+    }
 }
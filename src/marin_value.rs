@@ -1,15 +1,49 @@
+use std::convert::TryFrom;
+use std::fmt;
 use std::ops::Range;
+use std::time::Duration;
 use derive_more::From;
+use indexmap::IndexMap;
 
+use crate::range::MarinRange;
 
-#[derive(Debug, PartialEq, From)]
+
+#[derive(Debug, Clone, PartialEq, From)]
 pub enum MarinValue {
     String(String),
     Bool(bool),
     Int(i64),
     Float(f64),
+    /// `null`, `none` or `None`, an option that was explicitly cleared.
+    #[from(ignore)]
+    Null,
     List(Vec<MarinValue>),
-    Range(Range<i64>),
+    Range(MarinRange),
+    Duration(Duration),
+    /// Keys keep the order they were written in, a repeated key replaces the earlier value.
+    Map(IndexMap<String, MarinValue>),
+}
+
+impl MarinValue {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            MarinValue::String(_) => ValueKind::String,
+            MarinValue::Bool(_) => ValueKind::Bool,
+            MarinValue::Int(_) => ValueKind::Int,
+            MarinValue::Float(_) => ValueKind::Float,
+            MarinValue::Null => ValueKind::Null,
+            MarinValue::List(_) => ValueKind::List,
+            MarinValue::Range(_) => ValueKind::Range,
+            MarinValue::Duration(_) => ValueKind::Duration,
+            MarinValue::Map(_) => ValueKind::Map,
+        }
+    }
+}
+
+impl From<Range<i64>> for MarinValue {
+    fn from(range: Range<i64>) -> Self {
+        MarinValue::Range(range.into())
+    }
 }
 
 impl From<&str> for MarinValue {
@@ -17,3 +51,141 @@ impl From<&str> for MarinValue {
         MarinValue::String(s.into())
     }
 }
+
+/// The variant of a [`MarinValue`] without its contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    String,
+    Bool,
+    Int,
+    Float,
+    Null,
+    List,
+    Range,
+    Duration,
+    Map,
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ValueKind::String => "string",
+            ValueKind::Bool => "bool",
+            ValueKind::Int => "int",
+            ValueKind::Float => "float",
+            ValueKind::Null => "null",
+            ValueKind::List => "list",
+            ValueKind::Range => "range",
+            ValueKind::Duration => "duration",
+            ValueKind::Map => "map",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The type a value is expected to have.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    Any,
+    String,
+    Bool,
+    Int,
+    /// Ints are accepted as well.
+    Float,
+    Range,
+    Duration,
+    List(Box<ValueType>),
+    /// A map whose values all have the inner type.
+    Map(Box<ValueType>),
+}
+
+impl ValueType {
+    pub fn matches(&self, value: &MarinValue) -> bool {
+        match (self, value) {
+            (ValueType::Any, _) => true,
+            (ValueType::String, MarinValue::String(_)) => true,
+            (ValueType::Bool, MarinValue::Bool(_)) => true,
+            (ValueType::Int, MarinValue::Int(_)) => true,
+            (ValueType::Float, MarinValue::Float(_)) | (ValueType::Float, MarinValue::Int(_)) => true,
+            (ValueType::Range, MarinValue::Range(_)) => true,
+            (ValueType::Duration, MarinValue::Duration(_)) => true,
+            (ValueType::List(inner), MarinValue::List(list)) => list.iter().all(|v| inner.matches(v)),
+            (ValueType::Map(inner), MarinValue::Map(map)) => map.values().all(|v| inner.matches(v)),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValueType::Any => write!(f, "value"),
+            ValueType::String => write!(f, "string"),
+            ValueType::Bool => write!(f, "bool"),
+            ValueType::Int => write!(f, "int"),
+            ValueType::Float => write!(f, "float"),
+            ValueType::Range => write!(f, "range"),
+            ValueType::Duration => write!(f, "duration"),
+            ValueType::List(inner) => write!(f, "list of {}s", inner),
+            ValueType::Map(inner) => write!(f, "map of {}s", inner),
+        }
+    }
+}
+
+const DURATION_UNITS: [(&str, u64); 8] = [
+    ("w", 604_800_000_000_000),
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+/// The amounts and unit indices of a duration literal like `2w3d` that the grammar already accepted.
+fn duration_parts(literal: &str) -> impl Iterator<Item = (&str, usize)> {
+    let mut rest = literal;
+    std::iter::from_fn(move || {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let end = rest[digits..].find(|c: char| c.is_ascii_digit()).map_or(rest.len(), |i| i + digits);
+        let unit = DURATION_UNITS.iter().position(|(unit, _)| *unit == &rest[digits..end])?;
+        let part = (&rest[..digits], unit);
+        rest = &rest[end..];
+        Some(part)
+    })
+}
+
+/// Whether every unit of a duration literal is smaller than the one before.
+pub(crate) fn is_ordered_duration(literal: &str) -> bool {
+    let units: Vec<_> = duration_parts(literal).map(|(_, unit)| unit).collect();
+    units.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+/// Parse a duration literal, `None` if it does not fit into a [`Duration`].
+pub(crate) fn parse_duration(literal: &str) -> Option<Duration> {
+    let mut nanos = 0u128;
+    for (amount, unit) in duration_parts(literal) {
+        let amount: u128 = amount.parse().ok()?;
+        nanos = nanos.checked_add(amount.checked_mul(u128::from(DURATION_UNITS[unit].1))?)?;
+    }
+    let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+    Some(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+}
+
+/// The shortest duration literal for `duration`.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let mut nanos = duration.as_nanos();
+    if nanos == 0 {
+        return "0s".into();
+    }
+    let mut literal = String::new();
+    for (unit, factor) in DURATION_UNITS.iter() {
+        let factor = u128::from(*factor);
+        if nanos >= factor {
+            literal.push_str(&format!("{}{}", nanos / factor, unit));
+            nanos %= factor;
+        }
+    }
+    literal
+}
@@ -0,0 +1,71 @@
+/// Settings for [`Marin::parse_with`](crate::Marin::parse_with).
+///
+/// ```
+/// use marin::{Marin, MarinValue, ParseOptions};
+///
+/// let options = ParseOptions { durations: false, ..ParseOptions::default() };
+/// let m = Marin::parse_with("offset: 30m", &options).unwrap();
+/// assert_eq!(m.kwargs["offset"], MarinValue::from("30m"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Parse duration literals like `2w3d` as [`MarinValue::Duration`](crate::MarinValue::Duration),
+    /// otherwise they are kept as strings.
+    pub durations: bool,
+    /// What a keyword without a value like `reason:` means.
+    pub dangling_keywords: DanglingKeyword,
+    /// What happens to a keyword or flag that is given more than once.
+    pub duplicates: DuplicateKeywords,
+    /// Punctuation allowed in unquoted words, a subset of [`WORD_PUNCTUATION`].
+    ///
+    /// Letters, digits, emoji, `@`, `.` and `/` are always allowed.
+    pub punctuation: String,
+    /// Known keyword and flag names, unknown names close to one of them are reported as misspelt by
+    /// [`Marin::parse_checked_with`](crate::Marin::parse_checked_with).
+    pub keywords: Vec<String>,
+}
+
+/// Every punctuation character that can be allowed in unquoted words.
+pub const WORD_PUNCTUATION: &str = "_-#+=?&%!~*$^|<>;()";
+
+/// The punctuation allowed in unquoted words by default.
+pub const DEFAULT_PUNCTUATION: &str = "_-#+=?&%";
+
+/// The first character of `word` that is in [`WORD_PUNCTUATION`] but not in `allowed`, with its byte offset.
+pub(crate) fn disallowed_punctuation(word: &str, allowed: &str) -> Option<(usize, char)> {
+    word.char_indices().find(|(_, c)| WORD_PUNCTUATION.contains(*c) && !allowed.contains(*c))
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            durations: true,
+            dangling_keywords: DanglingKeyword::Error,
+            duplicates: DuplicateKeywords::LastWins,
+            punctuation: DEFAULT_PUNCTUATION.into(),
+            keywords: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DanglingKeyword {
+    /// The keyword is set to [`MarinValue::Null`](crate::MarinValue::Null).
+    Null,
+    /// Parsing fails with [`Error::MissingValue`](crate::Error::MissingValue).
+    Error,
+}
+
+/// What happens to a keyword given more than once.
+///
+/// Flags and keywords share their names, `-x` is short for `x: true`, so `x: 1 -x` gives `x` twice. A flag
+/// repeated with the same value like `-x -x` means the same as giving it once and is never a duplicate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeywords {
+    LastWins,
+    FirstWins,
+    /// Parsing fails with [`Error::DuplicateKeyword`](crate::Error::DuplicateKeyword).
+    Error,
+    /// All values are collected into a [`MarinValue::List`](crate::MarinValue::List).
+    Accumulate,
+}
@@ -2,16 +2,45 @@
 #[grammar = "grammar.pest"]
 pub struct MarinParser;
 
+impl Rule {
+    /// Plain-language name of the rule, used in diagnostics.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Rule::Marin => "arguments",
+            Rule::EOI => "end of input",
+            Rule::Keyword => "keyword argument",
+            Rule::List => "list",
+            Rule::Map => "map",
+            Rule::MapEntry => "map entry",
+            Rule::Value => "value",
+            Rule::Flag => "flag",
+            Rule::FlagInner | Rule::NegatedFlag => "flag name",
+            Rule::RangeExpr | Rule::RangeOp | Rule::RangeStart | Rule::RangeEnd => "range",
+            Rule::RangeStep => "range step",
+            Rule::Duration | Rule::DurationUnit => "duration",
+            Rule::String => "string",
+            Rule::Bareword | Rule::WordChar | Rule::Emoji | Rule::Punctuation => "word",
+            Rule::Inner | Rule::SingleInner => "closing quote",
+            Rule::Escape => "escape sequence",
+            Rule::Exp => "exponent",
+            Rule::Number | Rule::Float | Rule::Int => "number",
+            Rule::Bool => "boolean",
+            Rule::Null => "null",
+            Rule::COMMENT | Rule::WHITESPACE => "",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fmt;
 
-    use pest::Parser;
-    use pest::parses_to;
+    use pest::{consumes_to, parses_to};
 
     use crate::parser::MarinParser;
     use crate::parser::Rule;
 
+    #[allow(dead_code)]
     struct ParsesToDisplay<'i, R: pest::RuleType>(pest::iterators::Pair<'i, R>);
 
     impl<'i, R: pest::RuleType> fmt::Display for ParsesToDisplay<'i, R> {
@@ -20,6 +49,7 @@ mod tests {
         }
     }
 
+    #[allow(dead_code)]
     impl<'i, R: pest::RuleType> ParsesToDisplay<'i, R> {
         fn display(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
             let span = self.0.clone().as_span();
@@ -233,10 +263,10 @@ mod tests {
     fn ranges() {
         parses_to! {
             parser: MarinParser,
-            input:  "range: 1..10 -5..15 ..10",
+            input:  "range: 1..10 -5..=15 ..10 3.. 0..100:10",
             rule:   Rule::Marin,
             tokens: [
-                Marin(0, 24, [
+                Marin(0, 39, [
                     Keyword(0, 12, [
                         String(0, 5, [
                             Bareword(0, 5, [
@@ -244,29 +274,39 @@ mod tests {
                         ]),
                         Value(7, 12, [
                             RangeExpr(7, 12, [
-                                Range(7, 12, [
-                                    Number(7, 8, []),
-                                    Number(10, 12, []),
-                                ]),
+                                RangeStart(7, 8, []),
+                                RangeOp(8, 10, []),
+                                RangeEnd(10, 12, []),
                             ]),
                         ]),
                     ]),
-                    Value(13, 19, [
-                        RangeExpr(13, 19, [
-                            Range(13, 19, [
-                                Number(13, 15, []),
-                                Number(17, 19, []),
-                            ]),
+                    Value(13, 20, [
+                        RangeExpr(13, 20, [
+                            RangeStart(13, 15, []),
+                            RangeOp(15, 18, []),
+                            RangeEnd(18, 20, []),
                         ]),
                     ]),
-                    Value(20, 24, [
-                        RangeExpr(20, 24, [
-                            RangeTo(20, 24, [
-                                Number(22, 24, []),
-                            ]),
+                    Value(21, 25, [
+                        RangeExpr(21, 25, [
+                            RangeOp(21, 23, []),
+                            RangeEnd(23, 25, []),
                         ]),
                     ]),
-                    EOI(24, 24, []),
+                    Value(26, 29, [
+                        RangeExpr(26, 29, [
+                            RangeStart(26, 27, []),
+                        ]),
+                    ]),
+                    Value(30, 39, [
+                        RangeExpr(30, 39, [
+                            RangeStart(30, 31, []),
+                            RangeOp(31, 33, []),
+                            RangeEnd(33, 36, []),
+                            RangeStep(37, 39, []),
+                        ]),
+                    ]),
+                    EOI(39, 39, []),
                 ]),
             ]
         };
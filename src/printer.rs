@@ -0,0 +1,202 @@
+use std::fmt;
+
+use pest::Parser;
+
+use crate::marin_value::{format_duration, MarinValue};
+use crate::options::{disallowed_punctuation, DEFAULT_PUNCTUATION};
+use crate::parser::{MarinParser, Rule};
+use crate::Marin;
+
+/// Quote `s` unless it would be parsed back as the same bareword.
+pub(crate) fn quote(s: &str) -> String {
+    if is_plain_string(s) {
+        s.to_string()
+    } else {
+        let mut quoted = String::with_capacity(s.len() + 2);
+        quoted.push('"');
+        for c in s.chars() {
+            match c {
+                '\\' => quoted.push_str("\\\\"),
+                '"' => quoted.push_str("\\\""),
+                '\n' => quoted.push_str("\\n"),
+                '\t' => quoted.push_str("\\t"),
+                '\r' => quoted.push_str("\\r"),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+}
+
+/// Whether `s` parses as a string made of a single bareword.
+fn is_plain_string(s: &str) -> bool {
+    // A word like `:/x` after a space would be read as the colon of the argument before.
+    if s.starts_with(':') || disallowed_punctuation(s, DEFAULT_PUNCTUATION).is_some() {
+        return false;
+    }
+    match MarinParser::parse(Rule::Value, s) {
+        Ok(mut pairs) => {
+            let value = pairs.next();
+            let string = value.as_ref().and_then(|v| v.clone().into_inner().next());
+            let bareword = string.as_ref().and_then(|s| s.clone().into_inner().next());
+            match (value, string, bareword) {
+                (Some(value), Some(string), Some(bareword)) =>
+                    value.as_str() == s && string.as_rule() == Rule::String && bareword.as_rule() == Rule::Bareword,
+                _ => false,
+            }
+        }
+        Err(_) => false,
+    }
+}
+
+/// Whether `rule` matches all of `s` with the default punctuation.
+pub(crate) fn consumes(rule: Rule, s: &str) -> bool {
+    disallowed_punctuation(s, DEFAULT_PUNCTUATION).is_none() && MarinParser::parse(rule, s).ok()
+                               .and_then(|mut pairs| pairs.next())
+                               .is_some_and(|pair| pair.as_str() == s)
+}
+
+/// `-key` or `-no-key`, `None` if `key` is not a valid flag name.
+pub(crate) fn flag(key: &str, value: bool) -> Option<String> {
+    match value {
+        // `-no-x` would unset `x` instead of setting `no-x`.
+        true if consumes(Rule::FlagInner, key) && !key.starts_with("no-") => Some(format!("-{}", key)),
+        false if consumes(Rule::NegatedFlag, key) => Some(format!("-no-{}", key)),
+        _ => None,
+    }
+}
+
+/// Renders the value as Marin source, strings are only quoted when needed.
+///
+/// Floats that are not finite have no literal and do not parse back.
+impl fmt::Display for MarinValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MarinValue::String(s) => write!(f, "{}", quote(s)),
+            MarinValue::Bool(b) => write!(f, "{}", b),
+            MarinValue::Int(i) => write!(f, "{}", i),
+            MarinValue::Float(x) => write!(f, "{:?}", x),
+            MarinValue::Null => write!(f, "null"),
+            MarinValue::List(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            MarinValue::Range(range) => write!(f, "{}", range),
+            MarinValue::Duration(duration) => write!(f, "{}", format_duration(*duration)),
+            MarinValue::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", quote(key), value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Renders positional arguments followed by keyword arguments, booleans are written as flags.
+///
+/// Parsing the output gives back the same arguments.
+impl fmt::Display for Marin<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts: Vec<String> = self.args.iter().map(|value| value.to_string()).collect();
+        for (key, value) in self.kwargs.iter() {
+            match value {
+                MarinValue::Bool(b) if flag(key, *b).is_some() => parts.extend(flag(key, *b)),
+                value => parts.push(format!("{}: {}", quote(key), value)),
+            }
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::time::Duration;
+
+    use indexmap::IndexMap;
+    use proptest::prelude::*;
+
+    use crate::{Marin, MarinRange, MarinValue, OwnedMarin};
+
+    fn range() -> impl Strategy<Value = MarinRange> {
+        let bound = proptest::option::of(any::<i64>());
+        // Any bounds, inclusive or not, `from_bounds` refuses the ones that are not a range.
+        (bound.clone(), bound, any::<bool>(), proptest::option::of(1..=i64::MAX))
+            .prop_filter_map("no bounds", |(start, end, inclusive, step)| {
+                let range = MarinRange::from_bounds(start, end, inclusive)?;
+                Some(step.map_or(range, |step| range.with_step(step)))
+            })
+    }
+
+    fn value() -> impl Strategy<Value = MarinValue> {
+        let leaf = prop_oneof![
+            any::<String>().prop_map(MarinValue::String),
+            "[a-zü🦀@./:_#+%-]{1,8}".prop_map(MarinValue::String),
+            any::<bool>().prop_map(MarinValue::Bool),
+            any::<i64>().prop_map(MarinValue::Int),
+            any::<f64>().prop_filter("finite", |f| f.is_finite()).prop_map(MarinValue::Float),
+            Just(MarinValue::Null),
+            range().prop_map(MarinValue::Range),
+            (any::<u64>(), 0..1_000_000_000u32)
+                .prop_map(|(secs, nanos)| MarinValue::Duration(Duration::new(secs, nanos))),
+        ];
+        leaf.prop_recursive(4, 32, 4, |inner| prop_oneof![
+            proptest::collection::vec(inner.clone(), 0..4).prop_map(MarinValue::List),
+            proptest::collection::vec((any::<String>(), inner), 0..4)
+                .prop_map(|entries| MarinValue::Map(entries.into_iter().collect())),
+        ])
+    }
+
+    fn marin() -> impl Strategy<Value = OwnedMarin> {
+        let key = prop_oneof![any::<String>(), "(no-)?[a-zß][a-z0-9_-]{0,6}"];
+        (proptest::collection::vec(value(), 0..4), proptest::collection::vec((key, value()), 0..4)).prop_map(
+            |(args, kwargs)| Marin {
+                args,
+                kwargs: kwargs.into_iter().map(|(key, value)| (Cow::Owned(key), value)).collect::<IndexMap<_, _>>(),
+            },
+        )
+    }
+
+    #[test]
+    fn canonical_source() {
+        let m = Marin::parse("777000 \"ban reason\" reason: spam -silent !notify ids: [1, 2.5, \"a b\"] \
+                              range: 0..=10:2 offset: 1h30m limit: {\"1\": null}").unwrap();
+        assert_eq!(m.to_string(), "777000 \"ban reason\" reason: spam -silent -no-notify ids: [1, 2.5, \"a b\"] \
+                                   range: 0..=10:2 offset: 1h30m limit: {\"1\": null}");
+        assert_eq!(MarinValue::from("true").to_string(), "\"true\"");
+        assert_eq!(MarinValue::from("say \"hi\"").to_string(), "\"say \\\"hi\\\"\"");
+        assert_eq!(MarinValue::Float(1.0).to_string(), "1.0");
+        let quoted_keys = "no-x: true \"a \\\"b\\\"\": 1";
+        assert_eq!(Marin::parse(quoted_keys).unwrap().to_string(), quoted_keys);
+    }
+
+    proptest! {
+        #[test]
+        fn value_round_trip(value in value()) {
+            let source = value.to_string();
+            let parsed = Marin::parse(&source);
+            prop_assert!(parsed.is_ok(), "{:?} rendered as {:?}: {:?}", value, source, parsed);
+            prop_assert_eq!(parsed.unwrap().args, vec![value]);
+        }
+
+        #[test]
+        fn marin_round_trip(m in marin()) {
+            let source = m.to_string();
+            let parsed = Marin::parse(&source);
+            prop_assert!(parsed.is_ok(), "{:?} rendered as {:?}: {:?}", m, source, parsed);
+            prop_assert_eq!(parsed.unwrap(), m);
+        }
+    }
+}
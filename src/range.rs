@@ -0,0 +1,253 @@
+use std::fmt;
+use std::ops::{Range, RangeFrom, RangeInclusive, RangeTo, RangeToInclusive};
+
+/// A range literal that remembers which bounds were given, e.g. `1..`, `..=5` or `0..100:10`.
+///
+/// Every range has at least one bound and can be written as a literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MarinRange {
+    start: Option<i64>,
+    end: Option<i64>,
+    inclusive: bool,
+    step: Option<i64>,
+}
+
+impl MarinRange {
+    /// `start..end`
+    pub fn new(start: i64, end: i64) -> Self {
+        MarinRange { start: Some(start), end: Some(end), inclusive: false, step: None }
+    }
+
+    /// `start..=end`
+    pub fn inclusive(start: i64, end: i64) -> Self {
+        MarinRange { inclusive: true, ..MarinRange::new(start, end) }
+    }
+
+    /// A range with the given bounds, `None` if both are missing. `inclusive` only matters with an end.
+    pub fn from_bounds(start: Option<i64>, end: Option<i64>, inclusive: bool) -> Option<Self> {
+        match (start, end) {
+            (None, None) => None,
+            _ => Some(MarinRange { start, end, inclusive: inclusive && end.is_some(), step: None }),
+        }
+    }
+
+    /// Count in steps of `step` from the start, as in `0..100:10`.
+    ///
+    /// # Panics
+    ///
+    /// If `step` is not positive.
+    pub fn with_step(self, step: i64) -> Self {
+        assert!(step > 0, "the step of a range must be positive, got {}", step);
+        MarinRange { step: Some(step), ..self }
+    }
+
+    pub fn start(&self) -> Option<i64> {
+        self.start
+    }
+
+    pub fn end(&self) -> Option<i64> {
+        self.end
+    }
+
+    /// `end` is part of the range, as in `a..=b`.
+    pub fn is_inclusive(&self) -> bool {
+        self.inclusive
+    }
+
+    /// Always positive if present.
+    pub fn step(&self) -> Option<i64> {
+        self.step
+    }
+
+    /// The last value included by the end bound, ignoring the step.
+    fn last(&self) -> Option<i64> {
+        match self.end {
+            Some(end) if self.inclusive => Some(end),
+            Some(end) => Some(end.checked_sub(1)?),
+            None => Some(i64::MAX),
+        }
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        let after_start = self.start.iter().all(|&start| value >= start);
+        let before_end = self.last().is_some_and(|last| value <= last);
+        let on_step = match (self.start, self.step) {
+            (Some(start), Some(step)) => (i128::from(value) - i128::from(start)) % i128::from(step) == 0,
+            _ => true,
+        };
+        after_start && before_end && on_step
+    }
+
+    /// The values of the range, `None` if there is no start to count from.
+    pub fn iter(&self) -> Option<RangeIter> {
+        let next = self.start?;
+        Some(RangeIter { next: Some(next), last: self.last(), step: self.step.unwrap_or(1) })
+    }
+
+    /// `a..b` without a step.
+    pub fn as_range(&self) -> Option<Range<i64>> {
+        match (self.start, self.end, self.inclusive, self.step) {
+            (Some(start), Some(end), false, None) => Some(start..end),
+            _ => None,
+        }
+    }
+
+    /// `a..=b` without a step.
+    pub fn as_range_inclusive(&self) -> Option<RangeInclusive<i64>> {
+        match (self.start, self.end, self.inclusive, self.step) {
+            (Some(start), Some(end), true, None) => Some(start..=end),
+            _ => None,
+        }
+    }
+
+    /// `a..` without a step.
+    pub fn as_range_from(&self) -> Option<RangeFrom<i64>> {
+        match (self.start, self.end, self.step) {
+            (Some(start), None, None) => Some(start..),
+            _ => None,
+        }
+    }
+
+    /// `..b` without a step.
+    pub fn as_range_to(&self) -> Option<RangeTo<i64>> {
+        match (self.start, self.end, self.inclusive, self.step) {
+            (None, Some(end), false, None) => Some(..end),
+            _ => None,
+        }
+    }
+
+    /// `..=b` without a step.
+    pub fn as_range_to_inclusive(&self) -> Option<RangeToInclusive<i64>> {
+        match (self.start, self.end, self.inclusive, self.step) {
+            (None, Some(end), true, None) => Some(..=end),
+            _ => None,
+        }
+    }
+}
+
+impl From<Range<i64>> for MarinRange {
+    fn from(range: Range<i64>) -> Self {
+        MarinRange::new(range.start, range.end)
+    }
+}
+
+impl From<RangeInclusive<i64>> for MarinRange {
+    fn from(range: RangeInclusive<i64>) -> Self {
+        MarinRange::inclusive(*range.start(), *range.end())
+    }
+}
+
+impl From<RangeFrom<i64>> for MarinRange {
+    fn from(range: RangeFrom<i64>) -> Self {
+        MarinRange { start: Some(range.start), end: None, inclusive: false, step: None }
+    }
+}
+
+impl From<RangeTo<i64>> for MarinRange {
+    fn from(range: RangeTo<i64>) -> Self {
+        MarinRange { start: None, end: Some(range.end), inclusive: false, step: None }
+    }
+}
+
+impl From<RangeToInclusive<i64>> for MarinRange {
+    fn from(range: RangeToInclusive<i64>) -> Self {
+        MarinRange { start: None, end: Some(range.end), inclusive: true, step: None }
+    }
+}
+
+/// Renders the range as Marin source.
+impl fmt::Display for MarinRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(start) = self.start {
+            write!(f, "{}", start)?;
+        }
+        write!(f, "{}", if self.inclusive { "..=" } else { ".." })?;
+        if let Some(end) = self.end {
+            write!(f, "{}", end)?;
+        }
+        if let Some(step) = self.step {
+            write!(f, ":{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+impl IntoIterator for MarinRange {
+    type Item = i64;
+    type IntoIter = RangeIter;
+
+    /// Ranges without a start are empty.
+    fn into_iter(self) -> RangeIter {
+        self.iter().unwrap_or(RangeIter { next: None, last: None, step: 1 })
+    }
+}
+
+/// Iterator over the values of a [`MarinRange`].
+#[derive(Debug, Clone)]
+pub struct RangeIter {
+    next: Option<i64>,
+    last: Option<i64>,
+    step: i64,
+}
+
+impl Iterator for RangeIter {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        let value = self.next.filter(|value| self.last.is_some_and(|last| *value <= last))?;
+        self.next = value.checked_add(self.step);
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MarinRange;
+
+    #[test]
+    fn iteration() {
+        assert_eq!(MarinRange::new(1, 4).into_iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(MarinRange::inclusive(1, 4).into_iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(MarinRange::new(0, 100).with_step(30).into_iter().collect::<Vec<_>>(), vec![0, 30, 60, 90]);
+        assert_eq!(MarinRange::from(i64::MAX - 1..).into_iter().count(), 2);
+        assert_eq!(MarinRange::new(i64::MIN, i64::MIN).into_iter().count(), 0);
+        assert!(MarinRange::from(..5).iter().is_none());
+    }
+
+    #[test]
+    fn contains() {
+        let range = MarinRange::inclusive(0, 100).with_step(10);
+        assert!(range.contains(100));
+        assert!(!range.contains(15));
+        assert!(!range.contains(-10));
+        assert!(MarinRange::from(..=5).contains(i64::MIN));
+        assert!(!MarinRange::from(..5).contains(5));
+    }
+
+    #[test]
+    fn std_conversions() {
+        assert_eq!(MarinRange::new(1, 5).as_range(), Some(1..5));
+        assert_eq!(MarinRange::new(1, 5).as_range_inclusive(), None);
+        assert_eq!(MarinRange::inclusive(1, 5).as_range_inclusive(), Some(1..=5));
+        assert_eq!(MarinRange::from(1..).as_range_from(), Some(1..));
+        assert_eq!(MarinRange::from(..5).as_range_to(), Some(..5));
+        assert_eq!(MarinRange::from(..=5).as_range_to_inclusive(), Some(..=5));
+        assert_eq!(MarinRange::new(1, 5).with_step(2).as_range(), None);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(MarinRange::new(-5, 5).to_string(), "-5..5");
+        assert_eq!(MarinRange::inclusive(0, 100).with_step(10).to_string(), "0..=100:10");
+        assert_eq!(MarinRange::from(3..).to_string(), "3..");
+        assert_eq!(MarinRange::from(..=3).to_string(), "..=3");
+        assert_eq!(MarinRange::from_bounds(Some(1), None, true).unwrap().to_string(), "1..");
+        assert_eq!(MarinRange::from_bounds(None, None, false), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "the step of a range must be positive")]
+    fn zero_step() {
+        MarinRange::new(0, 10).with_step(0);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::error::Error;
+use crate::options::ParseOptions;
+use crate::span::Span;
+use crate::{Marin, OwnedMarin};
+
+/// The result of [`Marin::parse_recovering`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recovered {
+    /// Everything that could be parsed around the problems.
+    pub marin: OwnedMarin,
+    /// The problems in source order, empty if the whole input parsed.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Recovered {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.severity == Severity::Error)
+    }
+}
+
+/// Parse `input`, blanking out each bad part and parsing again until the rest is accepted.
+/// Warnings about the accepted rest are included.
+///
+/// Blanked parts are replaced by spaces so spans of later problems still point into `input`.
+pub(crate) fn parse_recovering(input: &str, options: &ParseOptions) -> Recovered {
+    let mut text = input.to_string();
+    let mut diagnostics = vec![];
+    let mut skipped: Vec<Span> = vec![];
+    loop {
+        let error = match Marin::parse_checked_with(&text, options) {
+            Ok((marin, warnings)) => {
+                let marin = marin.into_owned();
+                diagnostics.extend(warnings.iter().map(|warning| warning.to_diagnostic(input)));
+                diagnostics.sort_by_key(|d: &Diagnostic| d.span.start);
+                return Recovered { marin, diagnostics };
+            }
+            Err(error) => error,
+        };
+        let bad = match error.to_diagnostic(&text) {
+            // The value of the keyword was skipped and already reported.
+            Some(diagnostic) if is_missing_skipped_value(&error, input, &skipped) => diagnostic.span,
+            Some(diagnostic) => {
+                let bad = bad_part(&text, &error, &diagnostic);
+                // Point into `input`, the line in `text` may already be partly blanked. Parser errors
+                // are at a single position, they are widened to what is skipped.
+                let span = if let Error::Parser(_) = error { bad } else { diagnostic.span };
+                let mut located = Diagnostic::new(diagnostic.message, span, input);
+                located.expected = diagnostic.expected;
+                diagnostics.push(located);
+                bad
+            }
+            None => Span::new(0, text.len()),
+        };
+        skipped.push(bad);
+        if !blank(&mut text, bad) {
+            // Nothing left to skip in the bad part, give up on the rest of the input.
+            let end = text.len();
+            blank(&mut text, Span::new(0, end));
+        }
+    }
+}
+
+fn is_missing_skipped_value(error: &Error, input: &str, skipped: &[Span]) -> bool {
+    match error {
+        Error::MissingValue { span, .. } =>
+            skipped.iter().any(|s| s.start >= span.end && input[span.end..s.start].chars().all(is_space)),
+        _ => false,
+    }
+}
+
+/// The part of `text` to skip past `error`.
+fn bad_part(text: &str, error: &Error, diagnostic: &Diagnostic) -> Span {
+    let at = diagnostic.span.start;
+    match (error, diagnostic.expected.as_slice()) {
+        (Error::Parser(_), ["closing quote"]) => Span::new(at, line_end(text, at)),
+        (Error::Parser(_), ["closing bracket"]) | (Error::Parser(_), ["closing brace"]) => {
+            // The error can be in a nested list that is closed later on the line.
+            let start = unclosed_bracket(&text[..line_end(text, at)])
+                .filter(|start| *start <= at)
+                .or_else(|| unclosed_bracket(&text[..at]))
+                .unwrap_or(at);
+            Span::new(start, word(text, at).end)
+        }
+        (Error::Parser(_), _) => {
+            let bad = Span::new(at, word(text, at).end);
+            if bad.start < bad.end {
+                bad
+            } else {
+                // Nothing to skip at the error, the word before it is what went wrong.
+                word(text, text[..at].trim_end_matches(is_space).len())
+            }
+        }
+        (Error::DisallowedPunctuation { .. }, _) => word(text, at),
+        _ => diagnostic.span,
+    }
+}
+
+/// The run of non-whitespace around byte `at`, not crossing brackets or commas.
+fn word(text: &str, at: usize) -> Span {
+    let boundary = |c: char| is_space(c) || "[]{},".contains(c);
+    let start = text[..at].char_indices().rev().find(|(_, c)| boundary(*c)).map_or(0, |(i, c)| i + c.len_utf8());
+    let end = text[at..].find(is_space).map_or(text.len(), |i| at + i);
+    // A bad character that is itself a boundary, like a stray `]`, is still skipped.
+    Span::new(start.min(at), end)
+}
+
+/// Whitespace as the grammar sees it.
+fn is_space(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\r' | '\n')
+}
+
+fn line_end(text: &str, at: usize) -> usize {
+    text[at..].find('\n').map_or(text.len(), |i| at + i)
+}
+
+/// Byte offset of the innermost `[` or `{` of `text` that is never closed, brackets in strings do not count.
+fn unclosed_bracket(text: &str) -> Option<usize> {
+    let mut open = vec![];
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote.is_some() => escaped = true,
+            c if quote == Some(c) => quote = None,
+            _ if quote.is_some() => (),
+            '"' | '\'' => quote = Some(c),
+            '[' | '{' => open.push(i),
+            ']' | '}' => {
+                open.pop();
+            }
+            _ => (),
+        }
+    }
+    open.pop()
+}
+
+/// Replace `span` of `text` by spaces, keeping line breaks and byte offsets. Whether anything changed.
+fn blank(text: &mut String, span: Span) -> bool {
+    let part = &text[span.start..span.end];
+    if part.chars().all(is_space) {
+        return false;
+    }
+    let spaces: String = part.chars()
+                             .map(|c| if c == '\n' { "\n".to_string() } else { " ".repeat(c.len_utf8()) })
+                             .collect();
+    text.replace_range(span.start..span.end, &spaces);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::{Marin, MarinValue, Severity};
+
+    fn messages(input: &str) -> Vec<String> {
+        Marin::parse_recovering(input).diagnostics.into_iter().map(|d| d.message).collect()
+    }
+
+    #[test]
+    fn all_problems_in_one_pass() {
+        let input = "777000 -flag: 1 chats: [1, 2]] reason: spam ids: [3, 4";
+        let recovered = Marin::parse_recovering(input);
+        assert!(recovered.has_errors());
+        assert_eq!(recovered.marin.args, vec![MarinValue::Int(777000), MarinValue::Int(1)]);
+        assert_eq!(recovered.marin.kwargs["flag"], MarinValue::Bool(true));
+        assert_eq!(recovered.marin.kwargs["chats"], MarinValue::List(vec![MarinValue::Int(1), MarinValue::Int(2)]));
+        assert_eq!(recovered.marin.kwargs["reason"], MarinValue::from("spam"));
+
+        let spans: Vec<_> = recovered.diagnostics.iter().map(|d| d.span.as_str(input)).collect();
+        assert_eq!(spans, vec![":", "]", "[3, 4"]);
+        assert!(recovered.diagnostics.iter().all(|d| d.severity == Severity::Error));
+        assert!(recovered.diagnostics[1].message.starts_with("unexpected `]`"));
+        assert_eq!(recovered.diagnostics[2].message, "unterminated list, expected closing bracket `]`");
+    }
+
+    #[test]
+    fn unterminated_quotes_skip_the_line() {
+        let input = "1 \"ban reason\nreason: 'spam -silent";
+        let recovered = Marin::parse_recovering(input);
+        assert_eq!(recovered.marin.args, vec![MarinValue::Int(1)]);
+        assert!(recovered.marin.kwargs.is_empty());
+        assert_eq!(recovered.diagnostics[0].message, "unterminated string, expected closing quote `\"`");
+        assert_eq!(recovered.diagnostics[0].column, 3);
+        assert_eq!(recovered.diagnostics[1].line, 2);
+        assert_eq!(recovered.diagnostics[1].message, "unterminated string, expected closing quote `'`");
+    }
+
+    #[test]
+    fn semantic_errors() {
+        assert_eq!(messages("1 99999999999999999999 -x -no-x a~b \"\\q\""), vec![
+            "integer `99999999999999999999` does not fit into 64 bits".to_string(),
+            "flag `x` is both set and negated".into(),
+            "`~` is not allowed in unquoted words".into(),
+            "invalid escape sequence `\\q`".into(),
+        ]);
+        let recovered = Marin::parse_recovering("1 99999999999999999999 -x -no-x a~b");
+        assert_eq!(recovered.marin.args, vec![MarinValue::Int(1)]);
+        assert_eq!(recovered.marin.kwargs["x"], MarinValue::Bool(true));
+    }
+
+    #[test]
+    fn clean_input() {
+        let recovered = Marin::parse_recovering("1 reason: spam");
+        assert!(!recovered.has_errors());
+        assert_eq!(recovered.marin, Marin::parse("1 reason: spam").unwrap());
+        assert!(messages("").is_empty());
+        assert_eq!(messages("] 1 ]]").len(), 2);
+        assert_eq!(messages("overrides: {a: 1, b: [2] -silent"), vec!["unterminated map, expected closing brace `}`"]);
+    }
+
+    proptest! {
+        #[test]
+        fn recovers_from_anything(input in "[ a1:\u{3000}\n\\\"'\\[\\]{},~-]{0,24}") {
+            let recovered = Marin::parse_recovering(&input);
+            prop_assert_eq!(recovered.has_errors(), Marin::parse(&input).is_err());
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use std::borrow::Cow;
+
+use crate::args::{ArgError, ArgErrorKind, ArgKind};
+use crate::marin_value::{MarinValue, ValueType};
+use crate::span::{Span, SpannedArgument, SpannedMarin};
+use crate::warning;
+use crate::Marin;
+
+/// A single argument accepted by a [`Schema`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub kind: ArgKind,
+    pub value_type: ValueType,
+    pub required: bool,
+    pub default: Option<MarinValue>,
+    /// If not empty the value has to be one of these.
+    pub allowed: Vec<MarinValue>,
+    /// Bounds for numbers, applied to every number in a list.
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub help: String,
+}
+
+impl Arg {
+    fn new(name: impl Into<String>, kind: ArgKind, value_type: ValueType) -> Self {
+        Arg {
+            name: name.into(),
+            aliases: vec![],
+            kind,
+            value_type,
+            required: false,
+            default: None,
+            allowed: vec![],
+            min: None,
+            max: None,
+            help: String::new(),
+        }
+    }
+
+    pub fn positional(name: impl Into<String>, value_type: ValueType) -> Self {
+        Arg::new(name, ArgKind::Positional, value_type)
+    }
+
+    pub fn keyword(name: impl Into<String>, value_type: ValueType) -> Self {
+        Arg::new(name, ArgKind::Keyword, value_type)
+    }
+
+    pub fn flag(name: impl Into<String>) -> Self {
+        Arg::new(name, ArgKind::Flag, ValueType::Bool)
+    }
+
+    pub fn alias(mut self, alias: impl Into<String>) -> Self {
+        self.aliases.push(alias.into());
+        self
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub fn default(mut self, value: impl Into<MarinValue>) -> Self {
+        self.default = Some(value.into());
+        self
+    }
+
+    pub fn allowed<V: Into<MarinValue>>(mut self, values: impl IntoIterator<Item = V>) -> Self {
+        self.allowed = values.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn min(mut self, min: f64) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    pub fn max(mut self, max: f64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    pub fn help(mut self, help: impl Into<String>) -> Self {
+        self.help = help.into();
+        self
+    }
+
+    /// Whether `key` refers to this argument.
+    pub fn is_named(&self, key: &str) -> bool {
+        self.name == key || self.aliases.iter().any(|alias| alias == key)
+    }
+
+    fn check(&self, value: &MarinValue, span: Option<Span>, errors: &mut Vec<ArgError>) {
+        let error = |kind| ArgError { argument: self.name.clone(), kind, span, suggestion: None };
+        if *value == MarinValue::Null {
+            if self.required {
+                errors.push(error(ArgErrorKind::Missing));
+            }
+        } else if !self.value_type.matches(value) {
+            errors.push(error(ArgErrorKind::Mistyped { expected: self.value_type.to_string(), found: value.kind() }));
+        } else if !self.allowed.is_empty() && !self.allowed.contains(value) {
+            errors.push(error(ArgErrorKind::NotAllowed { allowed: self.allowed.clone() }));
+        } else if !self.in_bounds(value) {
+            errors.push(error(ArgErrorKind::OutOfRange { min: self.min, max: self.max }));
+        }
+    }
+
+    fn in_bounds(&self, value: &MarinValue) -> bool {
+        let number = match value {
+            MarinValue::Int(i) => *i as f64,
+            MarinValue::Float(f) => *f,
+            MarinValue::List(list) => return list.iter().all(|v| self.in_bounds(v)),
+            _ => return true,
+        };
+        self.min.iter().all(|&min| number >= min) && self.max.iter().all(|&max| number <= max)
+    }
+}
+
+/// Describes the arguments a command accepts, for commands that are registered at runtime.
+///
+/// ```
+/// use marin::{Arg, Marin, Schema, ValueType};
+///
+/// let schema = Schema::new()
+///     .arg(Arg::positional("user", ValueType::Int).required())
+///     .arg(Arg::keyword("chats", ValueType::List(Box::new(ValueType::Int))))
+///     .arg(Arg::flag("silent"));
+/// let errors = schema.validate(&Marin::parse("777000 chats: \"abc\"").unwrap()).unwrap_err();
+/// assert_eq!(errors[0].to_string(), "`chats`: expected list of ints, found string");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    arguments: Vec<Arg>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Schema::default()
+    }
+
+    pub fn arg(mut self, arg: Arg) -> Self {
+        self.arguments.push(arg);
+        self
+    }
+
+    /// All arguments in the order they were added.
+    pub fn arguments(&self) -> &[Arg] {
+        &self.arguments
+    }
+
+    pub fn positionals(&self) -> impl Iterator<Item = &Arg> {
+        self.arguments.iter().filter(|arg| arg.kind == ArgKind::Positional)
+    }
+
+    /// The keyword or flag called `key`, aliases included.
+    pub fn keyword(&self, key: &str) -> Option<&Arg> {
+        self.arguments.iter().find(|arg| arg.kind != ArgKind::Positional && arg.is_named(key))
+    }
+
+    /// The names and aliases of all keywords and flags.
+    pub fn keyword_names(&self) -> Vec<String> {
+        self.arguments.iter()
+            .filter(|arg| arg.kind != ArgKind::Positional)
+            .flat_map(|arg| std::iter::once(&arg.name).chain(&arg.aliases).cloned())
+            .collect()
+    }
+
+    /// Check `marin` against the schema, returning every violation.
+    pub fn validate(&self, marin: &Marin) -> Result<(), Vec<ArgError>> {
+        let args = marin.args.iter().map(|value| (value, None)).collect();
+        let kwargs = marin.kwargs.iter().map(|(key, value)| (key.as_ref(), value, None)).collect();
+        self.check(args, kwargs)
+    }
+
+    /// Like [`validate`](Schema::validate), but the errors point at the offending input.
+    pub fn validate_spanned(&self, marin: &SpannedMarin) -> Result<(), Vec<ArgError>> {
+        let mut args = vec![];
+        let mut kwargs = vec![];
+        for argument in &marin.arguments {
+            match argument {
+                SpannedArgument::Positional(value) => args.push((MarinValue::from(value.clone()), Some(value.span))),
+                SpannedArgument::Keyword { key, value } =>
+                    kwargs.push((key.node.as_ref(), MarinValue::from(value.clone()), Some(argument.span()))),
+                SpannedArgument::Flag { key, value, span } =>
+                    kwargs.push((key.node, MarinValue::Bool(*value), Some(*span))),
+            }
+        }
+        let args = args.iter().map(|(value, span)| (value, *span)).collect();
+        let kwargs = kwargs.iter().map(|(key, value, span)| (*key, value, *span)).collect();
+        self.check(args, kwargs)
+    }
+
+    /// Insert the defaults of all arguments that are absent from `marin`.
+    ///
+    /// Positional defaults fill the missing trailing slots in order, up to the first one without a default.
+    pub fn apply_defaults(&self, marin: &mut Marin<'_>) {
+        for arg in self.positionals().skip(marin.args.len()) {
+            match &arg.default {
+                Some(default) => marin.args.push(default.clone()),
+                None => break,
+            }
+        }
+        for arg in self.arguments.iter().filter(|arg| arg.kind != ArgKind::Positional) {
+            if let Some(default) = &arg.default {
+                if !marin.kwargs.keys().any(|key| arg.is_named(key)) {
+                    marin.kwargs.insert(Cow::Owned(arg.name.clone()), default.clone());
+                }
+            }
+        }
+    }
+
+    fn check(&self, args: Vec<(&MarinValue, Option<Span>)>, mut kwargs: Vec<(&str, &MarinValue, Option<Span>)>)
+             -> Result<(), Vec<ArgError>> {
+        let mut errors = vec![];
+        kwargs.sort_by_key(|(key, _, span)| (span.map(|s| s.start), *key));
+
+        let mut positionals = self.positionals();
+        for (index, (value, span)) in args.into_iter().enumerate() {
+            match positionals.next() {
+                Some(arg) => arg.check(value, span, &mut errors),
+                None => errors.push(ArgError {
+                    argument: format!("#{}", index + 1),
+                    kind: ArgErrorKind::Unexpected,
+                    span,
+                    suggestion: None,
+                }),
+            }
+        }
+        for arg in positionals.filter(|arg| arg.required) {
+            errors.push(ArgError::new(arg.name.clone(), ArgErrorKind::Missing));
+        }
+
+        let names = self.keyword_names();
+        for (key, value, span) in &kwargs {
+            match self.keyword(key) {
+                Some(arg) => arg.check(value, *span, &mut errors),
+                None => errors.push(ArgError {
+                    argument: key.to_string(),
+                    kind: ArgErrorKind::Unexpected,
+                    span: *span,
+                    suggestion: warning::closest(key, &names).map(String::from),
+                }),
+            }
+        }
+        for arg in self.arguments.iter().filter(|arg| arg.kind != ArgKind::Positional && arg.required) {
+            if !kwargs.iter().any(|(key, _, _)| arg.is_named(key)) {
+                errors.push(ArgError::new(arg.name.clone(), ArgErrorKind::Missing));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arg, ArgError, ArgErrorKind, Marin, MarinValue, OwnedMarin, Result, Schema, ValueKind, ValueType};
+
+    fn schema() -> Schema {
+        Schema::new()
+            .arg(Arg::positional("user", ValueType::Int).required())
+            .arg(Arg::keyword("reason", ValueType::String).alias("r").required())
+            .arg(Arg::keyword("chats", ValueType::List(Box::new(ValueType::Int))))
+            .arg(Arg::keyword("mode", ValueType::String).allowed(vec!["ban", "kick"]).default("ban"))
+            .arg(Arg::keyword("limit", ValueType::Int).min(1.0).max(100.0).default(10))
+            .arg(Arg::flag("silent"))
+    }
+
+    #[test]
+    fn valid() -> Result<()> {
+        assert_eq!(schema().validate(&Marin::parse("777000 r: spam chats: [1, 2] mode: kick -silent")?), Ok(()));
+        Ok(())
+    }
+
+    #[test]
+    fn all_violations_with_spans() -> Result<()> {
+        let input = "\"abc\" 2 chats: \"abc\" mode: mute limit: 500 -loud";
+        let errors = schema().validate_spanned(&Marin::parse_spanned(input)?).unwrap_err();
+        let spans: Vec<_> = errors.iter().map(|e| e.span.map(|s| s.as_str(input))).collect();
+        assert_eq!(errors.iter().map(|e| e.kind.clone()).collect::<Vec<_>>(), vec![
+            ArgErrorKind::Mistyped { expected: "int".into(), found: ValueKind::String },
+            ArgErrorKind::Unexpected,
+            ArgErrorKind::Mistyped { expected: "list of ints".into(), found: ValueKind::String },
+            ArgErrorKind::NotAllowed { allowed: vec!["ban".into(), "kick".into()] },
+            ArgErrorKind::OutOfRange { min: Some(1.0), max: Some(100.0) },
+            ArgErrorKind::Unexpected,
+            ArgErrorKind::Missing,
+        ]);
+        assert_eq!(spans, vec![
+            Some("\"abc\""), Some("2"), Some("chats: \"abc\""), Some("mode: mute"), Some("limit: 500"), Some("-loud"),
+            None,
+        ]);
+        assert_eq!(errors[2].to_string(), "`chats`: expected list of ints, found string");
+        assert_eq!(errors[3].to_string(), "`mode`: expected one of ban, kick");
+        assert_eq!(errors[4].to_string(), "`limit`: expected a number between 1 and 100");
+        Ok(())
+    }
+
+    #[test]
+    fn unspanned_errors() -> Result<()> {
+        let errors = schema().validate(&Marin::parse("1 reason: spam chats: [1, 2.5]")?).unwrap_err();
+        assert_eq!(errors, vec![ArgError {
+            argument: "chats".into(),
+            kind: ArgErrorKind::Mistyped { expected: "list of ints".into(), found: ValueKind::List },
+            span: None,
+            suggestion: None,
+        }]);
+        let errors = schema().validate_spanned(&Marin::parse_spanned("chats: [1]")?).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| e.kind == ArgErrorKind::Missing && e.span.is_none()));
+        Ok(())
+    }
+
+    #[test]
+    fn one_sided_bounds() -> Result<()> {
+        let schema = Schema::new()
+            .arg(Arg::keyword("limit", ValueType::Int).min(1.0))
+            .arg(Arg::keyword("ratio", ValueType::Float).max(0.5));
+        let errors = schema.validate(&Marin::parse("limit: 0 ratio: 0.75")?).unwrap_err();
+        assert_eq!(errors[0].to_string(), "`limit`: expected a number of at least 1");
+        assert_eq!(errors[1].to_string(), "`ratio`: expected a number of at most 0.5");
+        assert_eq!(schema.validate(&Marin::parse("limit: 1000 ratio: -1")?), Ok(()));
+        let error = ArgError::new("limit", ArgErrorKind::OutOfRange { min: None, max: None });
+        assert_eq!(error.to_string(), "`limit`: number is out of range");
+        Ok(())
+    }
+
+    #[test]
+    fn null_clears_optional_arguments() -> Result<()> {
+        assert_eq!(schema().validate(&Marin::parse("1 reason: spam chats: none")?), Ok(()));
+        let errors = schema().validate(&Marin::parse("1 reason: null")?).unwrap_err();
+        assert_eq!(errors, vec![ArgError::new("reason", ArgErrorKind::Missing)]);
+        Ok(())
+    }
+
+    #[test]
+    fn defaults() -> Result<()> {
+        let schema = schema();
+        let mut m = Marin::parse("1 reason: spam limit: 5")?;
+        schema.apply_defaults(&mut m);
+        assert_eq!(m.kwargs["mode"], MarinValue::from("ban"));
+        assert_eq!(m.kwargs["limit"], MarinValue::Int(5));
+        assert!(!m.kwargs.contains_key("silent"));
+        Ok(())
+    }
+
+    #[test]
+    fn positional_defaults() -> Result<()> {
+        let schema = Schema::new()
+            .arg(Arg::positional("user", ValueType::Int))
+            .arg(Arg::positional("count", ValueType::Int).default(1))
+            .arg(Arg::positional("unit", ValueType::String).default("msg"));
+        let mut m = Marin::parse("5 2")?;
+        schema.apply_defaults(&mut m);
+        assert_eq!(m.args, vec![MarinValue::Int(5), MarinValue::Int(2), MarinValue::from("msg")]);
+        // `user` has no default, so the later slots stay empty too.
+        let mut m = Marin::parse("")?;
+        schema.apply_defaults(&mut m);
+        assert!(m.args.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn defaults_outlive_the_schema() -> Result<()> {
+        let mut m: OwnedMarin = Marin::parse("1 reason: spam")?.into_owned();
+        {
+            let schema = schema();
+            schema.apply_defaults(&mut m);
+        }
+        assert_eq!(m.kwargs["mode"], MarinValue::from("ban"));
+        Ok(())
+    }
+}
@@ -0,0 +1,840 @@
+use std::convert::TryInto;
+use std::time::Duration;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::error::{Error, Result};
+use crate::marin_value::format_duration;
+use crate::parser::Rule;
+use crate::printer::{consumes, flag, quote};
+use crate::range::MarinRange;
+
+/// Render `value` as Marin source.
+///
+/// Struct fields and map entries become keyword arguments, booleans become `-flag` or
+/// `-no-flag` and the field `args` holds the positional arguments. Sequences and tuples
+/// at the top level are rendered as positional arguments only.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    let mut serializer = Serializer::new();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.output())
+}
+
+/// Serializes a struct, map or sequence into Marin arguments.
+pub struct Serializer {
+    positional: &'static str,
+    args: Vec<String>,
+    kwargs: Vec<String>,
+    key: Option<String>,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Serializer { positional: "args", args: vec![], kwargs: vec![], key: None }
+    }
+
+    /// Name of the field whose items are rendered as positional arguments.
+    pub fn positional_field(mut self, name: &'static str) -> Self {
+        self.positional = name;
+        self
+    }
+
+    /// The Marin source for everything serialized so far.
+    pub fn output(self) -> String {
+        let mut parts = self.args;
+        parts.extend(self.kwargs);
+        parts.join(" ")
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<()> {
+        if key == self.positional {
+            let mut positional = PositionalSerializer { args: vec![] };
+            value.serialize(&mut positional)?;
+            self.args.extend(positional.args);
+            return Ok(());
+        }
+        if !is_bareword(key) {
+            return Err(Error::Serialize(format!("`{}` can not be used as a keyword", key)));
+        }
+        match value.serialize(ValueSerializer)? {
+            Value::None => (),
+            Value::Bool(b) if flag(key, b).is_some() => self.kwargs.extend(flag(key, b)),
+            value => self.kwargs.push(format!("{}: {}", key, value.into_source())),
+        }
+        Ok(())
+    }
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Serializer::new()
+    }
+}
+
+/// A rendered value, options and booleans are kept apart so fields can turn into flags or be left out.
+enum Value {
+    None,
+    Bool(bool),
+    Source(String),
+}
+
+impl Value {
+    fn into_source(self) -> String {
+        match self {
+            Value::Bool(b) => b.to_string(),
+            Value::Source(s) => s,
+            Value::None => String::new(),
+        }
+    }
+}
+
+fn is_bareword(s: &str) -> bool {
+    consumes(Rule::Bareword, s)
+}
+
+fn float(f: f64) -> Result<String> {
+    if f.is_finite() {
+        Ok(format!("{:?}", f))
+    } else {
+        Err(Error::Serialize(format!("`{}` can not be represented", f)))
+    }
+}
+
+fn int<T: TryInto<i64> + std::fmt::Display + Copy>(i: T) -> Result<String> {
+    match i.try_into() {
+        Ok(i) => Ok(i.to_string()),
+        Err(_) => Err(Error::Serialize(format!("integer `{}` does not fit into 64 bits", i))),
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T> {
+    Err(Error::Serialize(format!("{} can not be represented", what)))
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = PositionalSeq<'a>;
+    type SerializeTuple = PositionalSeq<'a>;
+    type SerializeTupleStruct = PositionalSeq<'a>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<()> { unsupported("a top level bool") }
+    fn serialize_i8(self, _v: i8) -> Result<()> { unsupported("a top level integer") }
+    fn serialize_i16(self, _v: i16) -> Result<()> { unsupported("a top level integer") }
+    fn serialize_i32(self, _v: i32) -> Result<()> { unsupported("a top level integer") }
+    fn serialize_i64(self, _v: i64) -> Result<()> { unsupported("a top level integer") }
+    fn serialize_u8(self, _v: u8) -> Result<()> { unsupported("a top level integer") }
+    fn serialize_u16(self, _v: u16) -> Result<()> { unsupported("a top level integer") }
+    fn serialize_u32(self, _v: u32) -> Result<()> { unsupported("a top level integer") }
+    fn serialize_u64(self, _v: u64) -> Result<()> { unsupported("a top level integer") }
+    fn serialize_f32(self, _v: f32) -> Result<()> { unsupported("a top level float") }
+    fn serialize_f64(self, _v: f64) -> Result<()> { unsupported("a top level float") }
+    fn serialize_char(self, _v: char) -> Result<()> { unsupported("a top level char") }
+    fn serialize_str(self, _v: &str) -> Result<()> { unsupported("a top level string") }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<()> { unsupported("bytes") }
+    fn serialize_none(self) -> Result<()> { Ok(()) }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> { Ok(()) }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> { Ok(()) }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, _variant: &'static str) -> Result<()> {
+        unsupported("a top level enum")
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32,
+                                                        _variant: &'static str, _value: &T) -> Result<()> {
+        unsupported("a top level enum")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(PositionalSeq { args: &mut self.args })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+                               -> Result<Self::SerializeTupleVariant> {
+        unsupported("a top level enum")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+                                -> Result<Self::SerializeStructVariant> {
+        unsupported("a top level enum")
+    }
+}
+
+impl ser::SerializeStruct for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(ValueSerializer)? {
+            Value::Source(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => unsupported("a non-string keyword"),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        match self.key.take() {
+            Some(key) => self.field(&key, value),
+            None => unsupported("a value without a keyword"),
+        }
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Collects top level sequence items as positional arguments.
+pub struct PositionalSeq<'a> {
+    args: &'a mut Vec<String>,
+}
+
+impl PositionalSeq<'_> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        match value.serialize(ValueSerializer)? {
+            Value::None => unsupported("a missing positional argument"),
+            value => {
+                self.args.push(value.into_source());
+                Ok(())
+            }
+        }
+    }
+}
+
+impl ser::SerializeSeq for PositionalSeq<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for PositionalSeq<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for PositionalSeq<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Serializes the value of the positional field.
+struct PositionalSerializer {
+    args: Vec<String>,
+}
+
+impl<'a> ser::Serializer for &'a mut PositionalSerializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = PositionalSeq<'a>;
+    type SerializeTuple = PositionalSeq<'a>;
+    type SerializeTupleStruct = PositionalSeq<'a>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> { self.args.push(v.to_string()); Ok(()) }
+    fn serialize_i8(self, v: i8) -> Result<()> { self.serialize_i64(v.into()) }
+    fn serialize_i16(self, v: i16) -> Result<()> { self.serialize_i64(v.into()) }
+    fn serialize_i32(self, v: i32) -> Result<()> { self.serialize_i64(v.into()) }
+    fn serialize_i64(self, v: i64) -> Result<()> { self.args.push(v.to_string()); Ok(()) }
+    fn serialize_u8(self, v: u8) -> Result<()> { self.serialize_i64(v.into()) }
+    fn serialize_u16(self, v: u16) -> Result<()> { self.serialize_i64(v.into()) }
+    fn serialize_u32(self, v: u32) -> Result<()> { self.serialize_i64(v.into()) }
+    fn serialize_u64(self, v: u64) -> Result<()> { self.args.push(int(v)?); Ok(()) }
+    fn serialize_f32(self, v: f32) -> Result<()> { self.serialize_f64(v.into()) }
+    fn serialize_f64(self, v: f64) -> Result<()> { self.args.push(float(v)?); Ok(()) }
+    fn serialize_char(self, v: char) -> Result<()> { self.serialize_str(&v.to_string()) }
+    fn serialize_str(self, v: &str) -> Result<()> { self.args.push(quote(v)); Ok(()) }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<()> { unsupported("bytes") }
+    fn serialize_none(self) -> Result<()> { Ok(()) }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> { Ok(()) }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> { Ok(()) }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32,
+                                                        _variant: &'static str, _value: &T) -> Result<()> {
+        unsupported("an enum with data")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(PositionalSeq { args: &mut self.args })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+                               -> Result<Self::SerializeTupleVariant> {
+        unsupported("an enum with data")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        unsupported("a map as positional argument")
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        unsupported("a struct as positional argument")
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+                                -> Result<Self::SerializeStructVariant> {
+        unsupported("an enum with data")
+    }
+}
+
+/// Serializes a single value.
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = Impossible<Value, Error>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = Impossible<Value, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value> { Ok(Value::Bool(v)) }
+    fn serialize_i8(self, v: i8) -> Result<Value> { self.serialize_i64(v.into()) }
+    fn serialize_i16(self, v: i16) -> Result<Value> { self.serialize_i64(v.into()) }
+    fn serialize_i32(self, v: i32) -> Result<Value> { self.serialize_i64(v.into()) }
+    fn serialize_i64(self, v: i64) -> Result<Value> { Ok(Value::Source(v.to_string())) }
+    fn serialize_u8(self, v: u8) -> Result<Value> { self.serialize_i64(v.into()) }
+    fn serialize_u16(self, v: u16) -> Result<Value> { self.serialize_i64(v.into()) }
+    fn serialize_u32(self, v: u32) -> Result<Value> { self.serialize_i64(v.into()) }
+    fn serialize_u64(self, v: u64) -> Result<Value> { Ok(Value::Source(int(v)?)) }
+    fn serialize_f32(self, v: f32) -> Result<Value> { self.serialize_f64(v.into()) }
+    fn serialize_f64(self, v: f64) -> Result<Value> { Ok(Value::Source(float(v)?)) }
+    fn serialize_char(self, v: char) -> Result<Value> { self.serialize_str(&v.to_string()) }
+    fn serialize_str(self, v: &str) -> Result<Value> { Ok(Value::Source(quote(v))) }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Value> { unsupported("bytes") }
+    fn serialize_none(self) -> Result<Value> { Ok(Value::None) }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value> { Ok(Value::None) }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> { Ok(Value::None) }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Value> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32,
+                                                        _variant: &'static str, _value: &T) -> Result<Value> {
+        unsupported("an enum with data")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<ListSerializer> {
+        Ok(ListSerializer { items: vec![] })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ListSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<ListSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+                               -> Result<Self::SerializeTupleVariant> {
+        unsupported("an enum with data")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer> {
+        Ok(MapSerializer { entries: vec![], key: None })
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<StructSerializer> {
+        Ok(StructSerializer { name, fields: vec![], literal: true, map: MapSerializer { entries: vec![], key: None } })
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+                                -> Result<Self::SerializeStructVariant> {
+        unsupported("an enum with data")
+    }
+}
+
+struct ListSerializer {
+    items: Vec<String>,
+}
+
+impl ListSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        match value.serialize(ValueSerializer)? {
+            Value::None => self.items.push("null".into()),
+            value => self.items.push(value.into_source()),
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Value {
+        Value::Source(format!("[{}]", self.items.join(", ")))
+    }
+}
+
+impl ser::SerializeSeq for ListSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(ListSerializer::end(self))
+    }
+}
+
+impl ser::SerializeTuple for ListSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(ListSerializer::end(self))
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(ListSerializer::end(self))
+    }
+}
+
+/// Renders `{key: value, ...}`, entries whose value is `None` are left out.
+struct MapSerializer {
+    entries: Vec<String>,
+    key: Option<String>,
+}
+
+impl MapSerializer {
+    fn entry<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<()> {
+        match value.serialize(ValueSerializer)? {
+            Value::None => (),
+            value => self.entries.push(format!("{}: {}", key, value.into_source())),
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(ValueSerializer)? {
+            Value::Source(key) if consumes(Rule::String, &key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => unsupported("a map key that is not a string"),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        match self.key.take() {
+            Some(key) => self.entry(key, value),
+            None => unsupported("a map value without a key"),
+        }
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::Source(format!("{{{}}}", self.entries.join(", "))))
+    }
+}
+
+/// Nested structs are rendered as maps, except for ranges and durations which have their own literals.
+///
+/// Serde only tells the name of a struct, so the `std::ops` ranges and `std::time::Duration` are recognized by
+/// their name and fields. A struct of your own called `Duration` with integer `secs` and `nanos` fields and no
+/// others is rendered as a duration literal too.
+struct StructSerializer {
+    name: &'static str,
+    /// Integer fields of a range or duration.
+    fields: Vec<(&'static str, String)>,
+    /// Whether the fields so far fit the literal for `name`.
+    literal: bool,
+    map: MapSerializer,
+}
+
+impl StructSerializer {
+    fn field(&self, key: &str) -> Option<&str> {
+        self.fields.iter().find(|(k, _)| *k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// The fields of the struct with a literal called `name`.
+    fn literal_fields(&self) -> &'static [&'static str] {
+        match self.name {
+            "Duration" => &["secs", "nanos"],
+            "Range" | "RangeInclusive" => &["start", "end"],
+            "RangeFrom" => &["start"],
+            "RangeTo" => &["end"],
+            _ => &[],
+        }
+    }
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        match self.literal && self.literal_fields().contains(&key) {
+            true => match value.serialize(BoundSerializer) {
+                Ok(bound) => self.fields.push((key, bound)),
+                Err(_) => self.literal = false,
+            },
+            false => self.literal = false,
+        }
+        self.map.entry(quote(key), value)
+    }
+
+    fn end(self) -> Result<Value> {
+        if !self.literal || self.fields.len() != self.literal_fields().len() {
+            return ser::SerializeMap::end(self.map);
+        }
+        if self.name == "Duration" {
+            let secs = self.field("secs").and_then(|s| s.parse().ok());
+            let nanos = self.field("nanos").and_then(|n| n.parse().ok());
+            return match (secs, nanos) {
+                (Some(secs), Some(nanos)) if nanos < 1_000_000_000 =>
+                    Ok(Value::Source(format_duration(Duration::new(secs, nanos)))),
+                (Some(_), Some(_)) => unsupported("a duration with a second or more of nanoseconds"),
+                _ => unsupported("a duration without seconds"),
+            };
+        }
+        let start = self.field("start").and_then(|s| s.parse().ok());
+        let end = self.field("end").and_then(|e| e.parse().ok());
+        let bounded = matches!(
+            (self.name, start, end),
+            ("Range", Some(_), Some(_)) | ("RangeInclusive", Some(_), Some(_)) | ("RangeFrom", Some(_), None)
+            | ("RangeTo", None, Some(_))
+        );
+        match MarinRange::from_bounds(start, end, self.name == "RangeInclusive") {
+            Some(range) if bounded => Ok(Value::Source(range.to_string())),
+            _ => unsupported("a range without bounds"),
+        }
+    }
+}
+
+/// Accepts only the integers that are valid range bounds.
+struct BoundSerializer;
+
+impl ser::Serializer for BoundSerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<String> { unsupported("a non-integer range bound") }
+    fn serialize_i8(self, v: i8) -> Result<String> { int(v) }
+    fn serialize_i16(self, v: i16) -> Result<String> { int(v) }
+    fn serialize_i32(self, v: i32) -> Result<String> { int(v) }
+    fn serialize_i64(self, v: i64) -> Result<String> { int(v) }
+    fn serialize_u8(self, v: u8) -> Result<String> { int(v) }
+    fn serialize_u16(self, v: u16) -> Result<String> { int(v) }
+    fn serialize_u32(self, v: u32) -> Result<String> { int(v) }
+    fn serialize_u64(self, v: u64) -> Result<String> { int(v) }
+    fn serialize_f32(self, _v: f32) -> Result<String> { unsupported("a non-integer range bound") }
+    fn serialize_f64(self, _v: f64) -> Result<String> { unsupported("a non-integer range bound") }
+    fn serialize_char(self, _v: char) -> Result<String> { unsupported("a non-integer range bound") }
+    fn serialize_str(self, _v: &str) -> Result<String> { unsupported("a non-integer range bound") }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String> { unsupported("a non-integer range bound") }
+    fn serialize_none(self) -> Result<String> { unsupported("a non-integer range bound") }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String> { unsupported("a non-integer range bound") }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> { unsupported("a non-integer range bound") }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, _variant: &'static str) -> Result<String> {
+        unsupported("a non-integer range bound")
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32,
+                                                        _variant: &'static str, _value: &T) -> Result<String> {
+        unsupported("a non-integer range bound")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        unsupported("a non-integer range bound")
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        unsupported("a non-integer range bound")
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct> {
+        unsupported("a non-integer range bound")
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+                               -> Result<Self::SerializeTupleVariant> {
+        unsupported("a non-integer range bound")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        unsupported("a non-integer range bound")
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        unsupported("a non-integer range bound")
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+                                -> Result<Self::SerializeStructVariant> {
+        unsupported("a non-integer range bound")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::ops::Range;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{de, ser, Marin, Result};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct BanArgs {
+        args: Vec<i64>,
+        reason: String,
+        chats: Vec<i64>,
+        #[serde(default)]
+        silent: bool,
+        #[serde(default)]
+        notify: bool,
+        ids: Range<i64>,
+        limit: Option<u32>,
+        ratio: f64,
+    }
+
+    #[test]
+    fn struct_to_source() -> Result<()> {
+        let args = BanArgs {
+            args: vec![777000],
+            reason: "spam bot".into(),
+            chats: vec![-1001129887931, 2],
+            silent: true,
+            notify: false,
+            ids: -5..5,
+            limit: None,
+            ratio: 1.0,
+        };
+        let source = ser::to_string(&args)?;
+        assert_eq!(source, "777000 reason: \"spam bot\" chats: [-1001129887931, 2] -silent -no-notify \
+                            ids: -5..5 ratio: 1.0");
+        assert_eq!(de::from_str::<BanArgs>(&source)?, args);
+        Ok(())
+    }
+
+    #[test]
+    fn strings_are_quoted_when_needed() -> Result<()> {
+        let strings = vec![
+            "plain", "@username", "https://t.me/c/1129887931/26708", "", "with space", "123", "-5",
+            "1.5", "1..10", "true", "False", "say \"hi\"", "back\\slash", "spam[gban]", "a,b", "a: b",
+            "#hashtag", " padded ", "-flag", "line\nbreak", "Betrüger", "🦀", "2w3d", "none", "null",
+        ];
+        for s in strings {
+            let mut map = BTreeMap::new();
+            map.insert("reason", s);
+            let source = ser::to_string(&map)?;
+            let m = Marin::parse(&source)?;
+            assert_eq!(m.kwargs["reason"], s.into(), "{:?} rendered as {:?}", s, source);
+        }
+        assert_eq!(ser::to_string(&("plain", "two words"))?, "plain \"two words\"");
+        Ok(())
+    }
+
+    #[test]
+    fn unrepresentable_values() {
+        assert!(ser::to_string(&("nan", f64::NAN)).is_err());
+        assert!(ser::to_string(&(u64::MAX,)).is_err());
+        let mut map = BTreeMap::new();
+        map.insert("two words", 1);
+        assert!(ser::to_string(&map).is_err());
+    }
+
+    #[test]
+    fn nested_maps() -> Result<()> {
+        #[derive(Serialize)]
+        struct Override {
+            mode: &'static str,
+            limit: Option<u32>,
+        }
+
+        let mut overrides = BTreeMap::new();
+        overrides.insert("-100123", Override { mode: "kick", limit: Some(5) });
+        overrides.insert("other chat", Override { mode: "ban", limit: None });
+        let mut map = BTreeMap::new();
+        map.insert("overrides", overrides);
+        let source = ser::to_string(&map)?;
+        assert_eq!(source, "overrides: {\"-100123\": {mode: kick, limit: 5}, \"other chat\": {mode: ban}}");
+        assert!(Marin::parse(&source).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn range_shapes() -> Result<()> {
+        assert_eq!(ser::to_string(&(1..=5, 3.., ..10))?, "1..=5 3.. ..10");
+        Ok(())
+    }
+
+    #[test]
+    fn durations() -> Result<()> {
+        let mut map = BTreeMap::new();
+        map.insert("offset", std::time::Duration::from_secs(90 * 60));
+        map.insert("timeout", std::time::Duration::from_millis(1500));
+        let source = ser::to_string(&map)?;
+        assert_eq!(source, "offset: 1h30m timeout: 1s500ms");
+        assert_eq!(de::from_str::<BTreeMap<String, std::time::Duration>>(&source)?["offset"], map["offset"]);
+        assert_eq!(ser::to_string(&("short", std::time::Duration::from_nanos(1_500)))?, "short 1us500ns");
+        Ok(())
+    }
+
+    #[test]
+    fn structs_named_like_literals() -> Result<()> {
+        #[derive(Serialize)]
+        struct Duration {
+            secs: u64,
+            nanos: u32,
+            label: &'static str,
+        }
+        #[derive(Serialize)]
+        struct Range {
+            start: &'static str,
+            end: &'static str,
+        }
+
+        let mut map = BTreeMap::new();
+        map.insert("timeout", Duration { secs: 5, nanos: 0, label: "short" });
+        assert_eq!(ser::to_string(&map)?, "timeout: {secs: 5, nanos: 0, label: short}");
+        assert_eq!(ser::to_string(&(Range { start: "a", end: "z" },))?, "{start: a, end: z}");
+        Ok(())
+    }
+
+    #[test]
+    fn flag_names() -> Result<()> {
+        let mut map = BTreeMap::new();
+        map.insert("5ghz", true);
+        map.insert("mention", true);
+        assert_eq!(ser::to_string(&map)?, "5ghz: true -mention");
+        Ok(())
+    }
+}
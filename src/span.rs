@@ -0,0 +1,288 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+use crate::marin_value::MarinValue;
+use crate::options::DuplicateKeywords;
+use crate::range::MarinRange;
+use crate::Marin;
+
+/// Byte offsets of a token in the parsed input, `start` inclusive and `end` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// The text this span covers in `input`.
+    pub fn as_str<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.start..self.end]
+    }
+}
+
+impl From<pest::Span<'_>> for Span {
+    fn from(span: pest::Span<'_>) -> Self {
+        Span::new(span.start(), span.end())
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.start..span.end
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Self {
+        Spanned { node, span }
+    }
+}
+
+/// A [`MarinValue`] together with the span of the text it was parsed from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedValue {
+    pub kind: SpannedKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpannedKind {
+    String(String),
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// `span` is empty for the value of a dangling keyword.
+    Null,
+    List(Vec<SpannedValue>),
+    /// Bounds and step keep their own spans, absent bounds are `None`.
+    Range { start: Option<Spanned<i64>>, end: Option<Spanned<i64>>, inclusive: bool, step: Option<Spanned<i64>> },
+    Duration(Duration),
+    /// Entries in source order, repeated keys included.
+    Map(Vec<(Spanned<String>, SpannedValue)>),
+}
+
+impl From<SpannedValue> for MarinValue {
+    fn from(value: SpannedValue) -> Self {
+        match value.kind {
+            SpannedKind::String(s) => MarinValue::String(s),
+            SpannedKind::Bool(b) => MarinValue::Bool(b),
+            SpannedKind::Int(i) => MarinValue::Int(i),
+            SpannedKind::Float(f) => MarinValue::Float(f),
+            SpannedKind::Null => MarinValue::Null,
+            SpannedKind::List(l) => MarinValue::List(l.into_iter().map(Into::into).collect()),
+            SpannedKind::Range { start, end, inclusive, step } => {
+                // Only ranges built by hand can lack both bounds, they contain every number like `i64::MIN..`.
+                let range = MarinRange::from_bounds(start.map(|s| s.node), end.map(|e| e.node), inclusive)
+                    .unwrap_or_else(|| MarinRange::from(i64::MIN..));
+                match step.filter(|step| step.node > 0) {
+                    Some(step) => MarinValue::Range(range.with_step(step.node)),
+                    None => MarinValue::Range(range),
+                }
+            }
+            SpannedKind::Duration(d) => MarinValue::Duration(d),
+            SpannedKind::Map(entries) =>
+                MarinValue::Map(entries.into_iter().map(|(key, value)| (key.node, value.into())).collect()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpannedArgument<'a> {
+    Positional(SpannedValue),
+    /// Quoted keys are unescaped, `key.span` includes the quotes.
+    Keyword { key: Spanned<Cow<'a, str>>, value: SpannedValue },
+    /// `span` covers the leading `-`, `key` only the name. `value` is `false` for `-no-key` and `!key`.
+    Flag { key: Spanned<&'a str>, value: bool, span: Span },
+}
+
+impl SpannedArgument<'_> {
+    /// The span of the whole argument.
+    pub fn span(&self) -> Span {
+        match self {
+            SpannedArgument::Positional(value) => value.span,
+            SpannedArgument::Keyword { key, value } => Span::new(key.span.start, value.span.end),
+            SpannedArgument::Flag { span, .. } => *span,
+        }
+    }
+}
+
+/// The result of [`Marin::parse_spanned`], arguments are kept in source order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedMarin<'a> {
+    pub arguments: Vec<SpannedArgument<'a>>,
+}
+
+impl<'a> SpannedMarin<'a> {
+    /// Drop the spans, resolving repeated keywords according to `duplicates`.
+    pub fn into_marin(self, duplicates: DuplicateKeywords) -> Result<Marin<'a>> {
+        let mut marin = Marin { args: vec![], kwargs: Default::default() };
+        let mut accumulated = HashSet::new();
+        // Keys whose value was last set by a flag.
+        let mut flags = HashSet::new();
+        for argument in self.arguments {
+            let span = argument.span();
+            let (key, value) = match argument {
+                SpannedArgument::Positional(value) => {
+                    marin.args.push(value.into());
+                    continue;
+                }
+                SpannedArgument::Keyword { key, value } => {
+                    flags.remove(key.node.as_ref());
+                    (key.node, value.into())
+                }
+                SpannedArgument::Flag { key, value, .. } => {
+                    if !flags.insert(key.node) && marin.kwargs.get(key.node) == Some(&MarinValue::Bool(value)) {
+                        continue;
+                    }
+                    (Cow::Borrowed(key.node), MarinValue::Bool(value))
+                }
+            };
+            let previous = match marin.kwargs.get_mut(&key) {
+                Some(previous) => previous,
+                None => {
+                    marin.kwargs.insert(key, value);
+                    continue;
+                }
+            };
+            match duplicates {
+                DuplicateKeywords::LastWins => *previous = value,
+                DuplicateKeywords::FirstWins => (),
+                DuplicateKeywords::Error => return Err(Error::DuplicateKeyword { key: key.into_owned(), span }),
+                DuplicateKeywords::Accumulate => match previous {
+                    MarinValue::List(values) if accumulated.contains(&key) => values.push(value),
+                    _ => {
+                        *previous = MarinValue::List(vec![previous.clone(), value]);
+                        accumulated.insert(key);
+                    }
+                },
+            }
+        }
+        Ok(marin)
+    }
+}
+
+/// Repeated keywords are resolved with [`DuplicateKeywords::LastWins`].
+impl<'a> From<SpannedMarin<'a>> for Marin<'a> {
+    fn from(spanned: SpannedMarin<'a>) -> Self {
+        match spanned.into_marin(DuplicateKeywords::LastWins) {
+            Ok(marin) => marin,
+            Err(_) => unreachable!("last-wins never fails"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Marin, Result};
+    use crate::span::*;
+
+    #[test]
+    fn keyword_spans() -> Result<()> {
+        let input = "reason: \"spam bot\" 777000";
+        let m = Marin::parse_spanned(input)?;
+        match &m.arguments[0] {
+            SpannedArgument::Keyword { key, value } => {
+                assert_eq!(key.node, "reason");
+                assert_eq!(key.span.as_str(input), "reason");
+                assert_eq!(value.span.as_str(input), "\"spam bot\"");
+                assert_eq!(value.kind, SpannedKind::String("spam bot".into()));
+            }
+            other => panic!("expected keyword, got {:?}", other),
+        }
+        assert_eq!(m.arguments[1], SpannedArgument::Positional(SpannedValue {
+            kind: SpannedKind::Int(777000),
+            span: Span::new(19, 25),
+        }));
+        Ok(())
+    }
+
+    #[test]
+    fn flag_spans() -> Result<()> {
+        let m = Marin::parse_spanned("1 -mention -no-silent")?;
+        assert_eq!(m.arguments[1], SpannedArgument::Flag {
+            key: Spanned::new("mention", Span::new(3, 10)),
+            value: true,
+            span: Span::new(2, 10),
+        });
+        assert_eq!(m.arguments[2], SpannedArgument::Flag {
+            key: Spanned::new("silent", Span::new(15, 21)),
+            value: false,
+            span: Span::new(11, 21),
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn list_element_spans() -> Result<()> {
+        let input = "chats: [1, \"two\", 3]";
+        let m = Marin::parse_spanned(input)?;
+        let value = match &m.arguments[0] {
+            SpannedArgument::Keyword { value, .. } => value,
+            other => panic!("expected keyword, got {:?}", other),
+        };
+        assert_eq!(value.span.as_str(input), "[1, \"two\", 3]");
+        match &value.kind {
+            SpannedKind::List(list) => {
+                let texts: Vec<_> = list.iter().map(|v| v.span.as_str(input)).collect();
+                assert_eq!(texts, vec!["1", "\"two\"", "3"]);
+            }
+            other => panic!("expected list, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn range_bound_spans() -> Result<()> {
+        let input = "-5..15 ..=10 0..:5";
+        let m = Marin::parse_spanned(input)?;
+        assert_eq!(m.arguments[0], SpannedArgument::Positional(SpannedValue {
+            kind: SpannedKind::Range {
+                start: Some(Spanned::new(-5, Span::new(0, 2))),
+                end: Some(Spanned::new(15, Span::new(4, 6))),
+                inclusive: false,
+                step: None,
+            },
+            span: Span::new(0, 6),
+        }));
+        assert_eq!(m.arguments[1], SpannedArgument::Positional(SpannedValue {
+            kind: SpannedKind::Range {
+                start: None,
+                end: Some(Spanned::new(10, Span::new(10, 12))),
+                inclusive: true,
+                step: None,
+            },
+            span: Span::new(7, 12),
+        }));
+        assert_eq!(m.arguments[2], SpannedArgument::Positional(SpannedValue {
+            kind: SpannedKind::Range {
+                start: Some(Spanned::new(0, Span::new(13, 14))),
+                end: None,
+                inclusive: false,
+                step: Some(Spanned::new(5, Span::new(17, 18))),
+            },
+            span: Span::new(13, 18),
+        }));
+        Ok(())
+    }
+
+    #[test]
+    fn into_marin() -> Result<()> {
+        let input = "777000 \"ban reason\" -mention chats: [1, 2] range: ..5";
+        let spanned: Marin = Marin::parse_spanned(input)?.into();
+        assert_eq!(spanned, Marin::parse(input)?);
+        Ok(())
+    }
+}
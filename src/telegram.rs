@@ -0,0 +1,362 @@
+//! Typed Telegram values recognized in plain strings and ints.
+//!
+//! Nothing is recognized while parsing, handlers ask for these types instead of `String` or `i64`:
+//!
+//! ```
+//! use marin::Marin;
+//! use marin::telegram::{ChannelId, MessageLink, Username};
+//!
+//! let m = Marin::parse("@username link: https://t.me/c/1129887931/26708 chat: -1001129887931").unwrap();
+//! let user: Username = m.positional(0).unwrap().unwrap();
+//! let link: MessageLink = m.get("link").unwrap().unwrap();
+//! assert_eq!(user.0, "username");
+//! assert_eq!(link.message_id(), 26708);
+//! assert_eq!(m.get::<ChannelId>("chat").unwrap(), ChannelId::new(-1001129887931));
+//! ```
+use std::fmt;
+
+use crate::args::FromMarinValue;
+use crate::marin_value::{MarinValue, ValueType};
+
+/// Bot API ids of supergroups and channels are the peer id minus this.
+const CHANNEL_ID_OFFSET: i64 = 1_000_000_000_000;
+
+/// Paths of `t.me` links that are not usernames.
+const RESERVED_PATHS: &[&str] = &[
+    "joinchat", "addstickers", "addemoji", "addtheme", "addlist", "setlanguage", "share", "proxy", "socks", "login",
+    "confirmphone", "contact", "invoice", "boost",
+];
+
+/// A public username, written as `@name` or `t.me/name`, stored without the `@`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Username(pub String);
+
+/// The hash of an invite link like `t.me/joinchat/HASH` or `t.me/+HASH`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InviteHash(pub String);
+
+/// A supergroup or channel id in the Bot API form, e.g. `-1001129887931`.
+///
+/// Only `-100` followed by a peer id is a channel id, so it is always between `-1999999999999` and
+/// `-1000000000001`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChannelId(i64);
+
+/// The chat a [`MessageLink`] points into.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Chat {
+    /// From a private link like `t.me/c/1129887931/26708`.
+    Channel(ChannelId),
+    /// From a public link like `t.me/username/26708`.
+    Username(Username),
+}
+
+/// A link to a single message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MessageLink {
+    chat: Chat,
+    message_id: i64,
+}
+
+/// Any of the recognized values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TelegramValue {
+    Username(Username),
+    Invite(InviteHash),
+    Message(MessageLink),
+    Channel(ChannelId),
+}
+
+impl ChannelId {
+    /// `None` if `id` is not a channel id.
+    pub fn new(id: i64) -> Option<Self> {
+        match id.checked_add(CHANNEL_ID_OFFSET) {
+            Some(peer) if -CHANNEL_ID_OFFSET < peer && peer < 0 => Some(ChannelId(id)),
+            _ => None,
+        }
+    }
+
+    /// The Bot API id of the peer `peer_id`, `None` if it is out of range.
+    pub fn from_peer_id(peer_id: i64) -> Option<Self> {
+        if 0 < peer_id && peer_id < CHANNEL_ID_OFFSET {
+            Some(ChannelId(-CHANNEL_ID_OFFSET - peer_id))
+        } else {
+            None
+        }
+    }
+
+    /// The Bot API id, like `-1001129887931`.
+    pub fn id(&self) -> i64 {
+        self.0
+    }
+
+    /// The MTProto peer id, `1129887931` for `-1001129887931`.
+    pub fn peer_id(&self) -> i64 {
+        -CHANNEL_ID_OFFSET - self.0
+    }
+}
+
+impl MessageLink {
+    /// `None` if `message_id` is not positive.
+    pub fn new(chat: Chat, message_id: i64) -> Option<Self> {
+        if message_id > 0 {
+            Some(MessageLink { chat, message_id })
+        } else {
+            None
+        }
+    }
+
+    pub fn chat(&self) -> &Chat {
+        &self.chat
+    }
+
+    pub fn message_id(&self) -> i64 {
+        self.message_id
+    }
+}
+
+impl TelegramValue {
+    /// Recognize `value`, `None` if it is none of the Telegram values.
+    pub fn classify(value: &MarinValue) -> Option<Self> {
+        match value {
+            MarinValue::Int(id) => ChannelId::new(*id).map(TelegramValue::Channel),
+            MarinValue::String(s) => classify_str(s),
+            _ => None,
+        }
+    }
+}
+
+fn classify_str(s: &str) -> Option<TelegramValue> {
+    if let Some(name) = s.strip_prefix('@') {
+        return username(name).map(TelegramValue::Username);
+    }
+    if let Some(hash) = s.strip_prefix("tg://join?invite=") {
+        return invite_hash(hash).map(TelegramValue::Invite);
+    }
+    let mut parts = link_path(s)?.split('/');
+    match (parts.next()?, parts.next(), parts.next(), parts.next()) {
+        ("joinchat", Some(hash), None, None) => invite_hash(hash).map(TelegramValue::Invite),
+        ("c", Some(peer), None, None) => peer_id(peer).map(TelegramValue::Channel),
+        ("c", Some(peer), Some(message), None) => {
+            let chat = Chat::Channel(peer_id(peer)?);
+            MessageLink::new(chat, message.parse().ok()?).map(TelegramValue::Message)
+        }
+        (name, Some(message), None, None) => {
+            let chat = Chat::Username(username(name)?);
+            MessageLink::new(chat, message.parse().ok()?).map(TelegramValue::Message)
+        }
+        (name, None, None, None) => match name.strip_prefix('+') {
+            // `t.me/+4915112345678` links a phone number.
+            Some(hash) if !hash.bytes().all(|b| b.is_ascii_digit()) => invite_hash(hash).map(TelegramValue::Invite),
+            Some(_) => None,
+            None => username(name).map(TelegramValue::Username),
+        },
+        _ => None,
+    }
+}
+
+/// The part of a `t.me` link after the domain without query or trailing slash, `None` for other strings.
+fn link_path(s: &str) -> Option<&str> {
+    let s = s.strip_prefix("https://").or_else(|| s.strip_prefix("http://")).unwrap_or(s);
+    let path = ["t.me/", "telegram.me/", "telegram.dog/"].iter().find_map(|domain| s.strip_prefix(domain))?;
+    let path = path.split('?').next().unwrap_or(path);
+    Some(path.strip_suffix('/').unwrap_or(path))
+}
+
+fn username(name: &str) -> Option<Username> {
+    let valid = (5..=32).contains(&name.len())
+        && !RESERVED_PATHS.contains(&name)
+        && name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Some(Username(name.into()))
+    } else {
+        None
+    }
+}
+
+fn invite_hash(hash: &str) -> Option<InviteHash> {
+    if !hash.is_empty() && hash.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        Some(InviteHash(hash.into()))
+    } else {
+        None
+    }
+}
+
+fn peer_id(peer: &str) -> Option<ChannelId> {
+    ChannelId::from_peer_id(peer.parse().ok()?)
+}
+
+macro_rules! impl_from_marin_value {
+    ($($t:ty => $variant:ident, $value_type:expr, $expected:expr;)*) => {
+        $(
+            impl FromMarinValue for $t {
+                fn value_type() -> ValueType {
+                    $value_type
+                }
+
+                fn expected() -> String {
+                    $expected.into()
+                }
+
+                fn from_marin_value(value: &MarinValue) -> Option<Self> {
+                    match TelegramValue::classify(value)? {
+                        TelegramValue::$variant(value) => Some(value),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_marin_value! {
+    Username => Username, ValueType::String, "username like `@name`";
+    InviteHash => Invite, ValueType::String, "invite link like `t.me/+HASH`";
+    MessageLink => Message, ValueType::String, "message link like `t.me/c/1129887931/26708`";
+    ChannelId => Channel, ValueType::Int, "channel id like `-1001129887931`";
+}
+
+impl FromMarinValue for TelegramValue {
+    fn value_type() -> ValueType {
+        ValueType::Any
+    }
+
+    fn expected() -> String {
+        "username, Telegram link or channel id".into()
+    }
+
+    fn from_marin_value(value: &MarinValue) -> Option<Self> {
+        TelegramValue::classify(value)
+    }
+}
+
+impl fmt::Display for Username {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "@{}", self.0)
+    }
+}
+
+impl fmt::Display for InviteHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "https://t.me/+{}", self.0)
+    }
+}
+
+impl fmt::Display for ChannelId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for MessageLink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.chat {
+            Chat::Channel(channel) => write!(f, "https://t.me/c/{}/{}", channel.peer_id(), self.message_id),
+            Chat::Username(username) => write!(f, "https://t.me/{}/{}", username.0, self.message_id),
+        }
+    }
+}
+
+impl fmt::Display for TelegramValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TelegramValue::Username(username) => write!(f, "{}", username),
+            TelegramValue::Invite(invite) => write!(f, "{}", invite),
+            TelegramValue::Message(link) => write!(f, "{}", link),
+            TelegramValue::Channel(channel) => write!(f, "{}", channel),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::telegram::{Chat, ChannelId, InviteHash, MessageLink, TelegramValue, Username};
+    use crate::{ConversionError, Marin, MarinValue, ValueKind};
+
+    fn classify(input: &str) -> Option<TelegramValue> {
+        TelegramValue::classify(&Marin::parse(input).unwrap().args[0])
+    }
+
+    #[test]
+    fn usernames() {
+        let username = Some(TelegramValue::Username(Username("user_name".into())));
+        assert_eq!(classify("@user_name"), username);
+        assert_eq!(classify("https://t.me/user_name"), username);
+        assert_eq!(classify("telegram.me/user_name/"), username);
+        assert_eq!(classify("@abc"), None);
+        assert_eq!(classify("@1abcdef"), None);
+        assert_eq!(classify("user_name"), None);
+    }
+
+    #[test]
+    fn invites() {
+        let invite = Some(TelegramValue::Invite(InviteHash("CkzknkNYuLsKbTc91GfhGw".into())));
+        assert_eq!(classify("https://t.me/joinchat/CkzknkNYuLsKbTc91GfhGw"), invite);
+        assert_eq!(classify("t.me/+CkzknkNYuLsKbTc91GfhGw"), invite);
+        assert_eq!(classify("tg://join?invite=CkzknkNYuLsKbTc91GfhGw"), invite);
+        assert_eq!(classify("t.me/+4915112345678"), None);
+        assert_eq!(InviteHash("CkzknkNYuLsKbTc91GfhGw".into()).to_string(), "https://t.me/+CkzknkNYuLsKbTc91GfhGw");
+    }
+
+    #[test]
+    fn message_links() {
+        let channel = Chat::Channel(ChannelId::new(-1001129887931).unwrap());
+        let private = TelegramValue::Message(MessageLink::new(channel, 26708).unwrap());
+        assert_eq!(classify("https://t.me/c/1129887931/26708"), Some(private.clone()));
+        assert_eq!(classify("t.me/c/1129887931/26708?single"), Some(private.clone()));
+        assert_eq!(private.to_string(), "https://t.me/c/1129887931/26708");
+
+        let public = MessageLink::new(Chat::Username(Username("durov_says".into())), 5).unwrap();
+        assert_eq!(classify("t.me/durov_says/5"), Some(TelegramValue::Message(public)));
+        assert_eq!(classify("t.me/c/1129887931/0"), None);
+        assert_eq!(classify("t.me/c/1129887931/-5"), None);
+        assert_eq!(MessageLink::new(Chat::Username(Username("durov_says".into())), -5), None);
+        assert_eq!(classify("t.me/c/abc/1"), None);
+    }
+
+    #[test]
+    fn channel_ids() {
+        let channel = ChannelId::new(-1001129887931).unwrap();
+        assert_eq!(classify("-1001129887931"), Some(TelegramValue::Channel(channel)));
+        assert_eq!(classify("t.me/c/1129887931"), Some(TelegramValue::Channel(channel)));
+        assert_eq!(channel.peer_id(), 1129887931);
+        assert_eq!(channel.id(), -1001129887931);
+        assert_eq!(classify("777000"), None);
+        assert_eq!(classify("-123456789"), None);
+
+        let highest = ChannelId::from_peer_id(999_999_999_999).unwrap();
+        assert_eq!(classify("-1999999999999"), Some(TelegramValue::Channel(highest)));
+        assert_eq!(classify("-2000000000000"), None);
+        assert_eq!(classify("-9223372036854775808"), None);
+        assert_eq!(classify("t.me/c/1000000000000"), None);
+        assert_eq!(ChannelId::new(i64::MAX), None);
+        assert_eq!(ChannelId::new(-1_000_000_000_000), None);
+        assert_eq!(ChannelId::from_peer_id(i64::MAX), None);
+    }
+
+    #[test]
+    fn reserved_paths() {
+        assert_eq!(classify("t.me/joinchat"), None);
+        assert_eq!(classify("https://t.me/joinchat/"), None);
+        assert_eq!(classify("t.me/c"), None);
+        assert_eq!(classify("t.me/+"), None);
+        assert_eq!(classify("t.me/addstickers"), None);
+        assert_eq!(classify("t.me/share/5"), None);
+        assert_eq!(classify("@joinchat"), None);
+    }
+
+    #[test]
+    fn typed_access() {
+        let m = Marin::parse("@username -1001129887931 link: t.me/c/1129887931/26708 chat: 777000").unwrap();
+        assert_eq!(m.positional::<Username>(0).unwrap(), Some(Username("username".into())));
+        assert_eq!(m.positional::<ChannelId>(1).unwrap(), Some(ChannelId::new(-1001129887931).unwrap()));
+        assert_eq!(m.get::<MessageLink>("link").unwrap().map(|link| link.message_id()), Some(26708));
+        assert_eq!(m.get::<ChannelId>("chat"), Err(ConversionError {
+            key: Some("chat".into()),
+            expected: "channel id like `-1001129887931`".into(),
+            found: ValueKind::Int,
+        }));
+        assert!(TelegramValue::classify(&MarinValue::Bool(true)).is_none());
+    }
+}
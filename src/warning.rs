@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::options::{DuplicateKeywords, ParseOptions};
+use crate::range::MarinRange;
+use crate::span::{Span, SpannedArgument, SpannedKind, SpannedMarin, SpannedValue};
+
+/// Input that parses but is probably a mistake, see [`Marin::parse_checked`](crate::Marin::parse_checked).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+    pub span: Span,
+    /// Replacement for the text of `span`, like `reason` for a misspelt `reson`.
+    pub suggestion: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningCode {
+    /// A keyword given more than once, `span` is the repeated argument.
+    DuplicateKeyword,
+    /// A keyword or flag name that is not in [`ParseOptions::keywords`] but close to one that is.
+    MisspeltKeyword,
+    /// A list without elements.
+    EmptyList,
+    /// A range whose start is after its end, like `10..1`.
+    ReversedRange,
+}
+
+impl WarningCode {
+    /// A stable name for the code, like `misspelt-keyword`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WarningCode::DuplicateKeyword => "duplicate-keyword",
+            WarningCode::MisspeltKeyword => "misspelt-keyword",
+            WarningCode::EmptyList => "empty-list",
+            WarningCode::ReversedRange => "reversed-range",
+        }
+    }
+}
+
+impl fmt::Display for WarningCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Warning {
+    fn new(code: WarningCode, message: String, span: Span) -> Self {
+        Warning { code, message, span, suggestion: None }
+    }
+
+    /// A [`Diagnostic`] with [`Severity::Warning`] pointing into `input`, the string that was parsed.
+    pub fn to_diagnostic(&self, input: &str) -> Diagnostic {
+        let mut diagnostic = Diagnostic::new(self.message.clone(), self.span, input);
+        diagnostic.severity = Severity::Warning;
+        diagnostic
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [{}]", self.message, self.code)
+    }
+}
+
+pub(crate) fn check(marin: &SpannedMarin, options: &ParseOptions) -> Vec<Warning> {
+    let mut warnings = vec![];
+    // The value of each name if it was last set by a flag, repeating a flag is not a duplicate.
+    let mut seen = HashMap::new();
+    for argument in &marin.arguments {
+        match argument {
+            SpannedArgument::Positional(value) => check_value(value, &mut warnings),
+            SpannedArgument::Keyword { key, value } => {
+                check_name("keyword", &key.node, key.span, options, &mut warnings);
+                if seen.insert(key.node.as_ref(), None).is_some() {
+                    check_duplicate(&key.node, argument.span(), options, &mut warnings);
+                }
+                check_value(value, &mut warnings);
+            }
+            SpannedArgument::Flag { key, value, .. } => {
+                check_name("flag", key.node, key.span, options, &mut warnings);
+                match seen.insert(key.node, Some(*value)) {
+                    Some(Some(previous)) if previous == *value => (),
+                    Some(_) => check_duplicate(key.node, argument.span(), options, &mut warnings),
+                    None => (),
+                }
+            }
+        }
+    }
+    warnings
+}
+
+fn check_duplicate(key: &str, span: Span, options: &ParseOptions, warnings: &mut Vec<Warning>) {
+    let used = match options.duplicates {
+        DuplicateKeywords::LastWins => "the last value is used",
+        DuplicateKeywords::FirstWins => "only the first value is used",
+        DuplicateKeywords::Error | DuplicateKeywords::Accumulate => return,
+    };
+    let message = format!("keyword `{}` is given more than once, {}", key, used);
+    warnings.push(Warning::new(WarningCode::DuplicateKeyword, message, span));
+}
+
+fn check_name(kind: &str, name: &str, span: Span, options: &ParseOptions, warnings: &mut Vec<Warning>) {
+    if options.keywords.is_empty() || options.keywords.iter().any(|known| known == name) {
+        return;
+    }
+    if let Some(known) = closest(name, &options.keywords) {
+        let message = format!("unknown {} `{}`, did you mean `{}`?", kind, name, known);
+        let warning = Warning::new(WarningCode::MisspeltKeyword, message, span);
+        warnings.push(Warning { suggestion: Some(known.into()), ..warning });
+    }
+}
+
+fn check_value(value: &SpannedValue, warnings: &mut Vec<Warning>) {
+    match &value.kind {
+        SpannedKind::List(values) if values.is_empty() =>
+            warnings.push(Warning::new(WarningCode::EmptyList, "empty list".into(), value.span)),
+        SpannedKind::List(values) => values.iter().for_each(|value| check_value(value, warnings)),
+        SpannedKind::Map(entries) => entries.iter().for_each(|(_, value)| check_value(value, warnings)),
+        SpannedKind::Range { start: Some(start), end: Some(end), inclusive, step } if start.node > end.node => {
+            let reversed = match *inclusive {
+                true => MarinRange::inclusive(end.node, start.node),
+                false => MarinRange::new(end.node, start.node),
+            };
+            let reversed = match step.as_ref().filter(|step| step.node > 0) {
+                Some(step) => reversed.with_step(step.node),
+                None => reversed,
+            };
+            let message = format!("range is empty because its start is after its end, did you mean `{}`?", reversed);
+            let warning = Warning::new(WarningCode::ReversedRange, message, value.span);
+            warnings.push(Warning { suggestion: Some(reversed.to_string()), ..warning });
+        }
+        _ => (),
+    }
+}
+
+/// The known name closest to `name`, if it is only a typo or two away.
+pub(crate) fn closest<'k>(name: &str, known: &'k [String]) -> Option<&'k str> {
+    let limit = if name.chars().count() <= 4 { 1 } else { 2 };
+    known.iter()
+         .map(|known| (edit_distance(name, known), known))
+         .filter(|(distance, _)| *distance <= limit)
+         .min_by_key(|(distance, _)| *distance)
+         .map(|(_, known)| known.as_str())
+}
+
+/// Edits to turn `a` into `b`, counting insertions, deletions, substitutions and swaps of neighbours.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substitution = rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            row[j] = substitution.min(rows[i - 1][j] + 1).min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::warning::edit_distance;
+    use crate::{DuplicateKeywords, Marin, MarinValue, ParseOptions, Severity, WarningCode};
+
+    fn options() -> ParseOptions {
+        ParseOptions { keywords: vec!["reason".into(), "silent".into(), "ids".into()], ..ParseOptions::default() }
+    }
+
+    #[test]
+    fn misspelt_keywords() {
+        let input = "777000 reson: spam -slient -silent ids: [1] idss: [2] -x";
+        let (m, warnings) = Marin::parse_checked_with(input, &options()).unwrap();
+        assert_eq!(m.kwargs["reson"], MarinValue::from("spam"));
+        let found: Vec<_> = warnings.iter().map(|w| (w.code, w.span.as_str(input), w.suggestion.as_deref())).collect();
+        assert_eq!(found, vec![
+            (WarningCode::MisspeltKeyword, "reson", Some("reason")),
+            (WarningCode::MisspeltKeyword, "slient", Some("silent")),
+            (WarningCode::MisspeltKeyword, "idss", Some("ids")),
+        ]);
+        assert_eq!(warnings[0].message, "unknown keyword `reson`, did you mean `reason`?");
+        assert_eq!(warnings[1].to_string(), "unknown flag `slient`, did you mean `silent`? [misspelt-keyword]");
+
+        let (_, warnings) = Marin::parse_checked("reson: spam").unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn duplicates() {
+        let input = "reason: spam 1 reason: flood";
+        let (m, warnings) = Marin::parse_checked(input).unwrap();
+        assert_eq!(m.kwargs["reason"], MarinValue::from("flood"));
+        assert_eq!(warnings[0].code, WarningCode::DuplicateKeyword);
+        assert_eq!(warnings[0].span.as_str(input), "reason: flood");
+        assert_eq!(warnings[0].message, "keyword `reason` is given more than once, the last value is used");
+
+        let options = ParseOptions { duplicates: DuplicateKeywords::Accumulate, ..ParseOptions::default() };
+        assert!(Marin::parse_checked_with(input, &options).unwrap().1.is_empty());
+        let (_, warnings) = Marin::parse_checked("silent: false -silent -x -x").unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].span.as_str("silent: false -silent -x -x"), "-silent");
+        let options = ParseOptions { duplicates: DuplicateKeywords::Error, ..ParseOptions::default() };
+        assert!(Marin::parse_checked_with(input, &options).is_err());
+    }
+
+    #[test]
+    fn empty_lists_and_reversed_ranges() {
+        let input = "[] ids: [1, []] range: 10..=1:2 ok: 1..10 map: {a: 5..-5}";
+        let (_, warnings) = Marin::parse_checked(input).unwrap();
+        let found: Vec<_> = warnings.iter().map(|w| (w.code, w.span.as_str(input), w.suggestion.as_deref())).collect();
+        assert_eq!(found, vec![
+            (WarningCode::EmptyList, "[]", None),
+            (WarningCode::EmptyList, "[]", None),
+            (WarningCode::ReversedRange, "10..=1:2", Some("1..=10:2")),
+            (WarningCode::ReversedRange, "5..-5", Some("-5..5")),
+        ]);
+        assert_eq!(warnings[1].span.start, 12);
+    }
+
+    #[test]
+    fn recovered_warnings() {
+        let recovered = Marin::parse_recovering("ids: [] ]");
+        let severities: Vec<_> = recovered.diagnostics.iter().map(|d| (d.severity, d.message.as_str())).collect();
+        assert_eq!(severities[0], (Severity::Warning, "empty list"));
+        assert_eq!(severities[1].0, Severity::Error);
+        assert!(recovered.diagnostics[0].to_string().starts_with("warning: empty list at line 1, column 6"));
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("reason", "reason"), 0);
+        assert_eq!(edit_distance("reson", "reason"), 1);
+        assert_eq!(edit_distance("slient", "silent"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}